@@ -1,33 +1,81 @@
 use crate::syntax::SyntaxFile;
 use crossterm::style::Color;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 use hex_color::HexColor;
 use serde::Deserialize;
 use litemap::LiteMap;
 use std::{fs, env};
+use std::time::Duration;
 use crate::alert;
 
 type Rgb = (u8, u8, u8);
 
-static CONFIG: OnceLock<Config> = OnceLock::new();
+// configs are leaked so that getters can hand out static references;
+// a new one is only created when the user reloads the config.
+static CONFIG: RwLock<Option<&'static Config>> = RwLock::new(None);
+
+// hidden tree entries are shown until this is toggled back
+static SHOW_HIDDEN: AtomicBool = AtomicBool::new(false);
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "kebab-case")]
 struct Config {
     syntax: LiteMap<String, HexColor>,
     general: General,
+    #[serde(default)]
+    tree: Tree,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "kebab-case")]
 struct General {
     background: Option<HexColor>,
+    selection: Option<HexColor>,
     syntax_file: Option<String>,
+    // older name of `tree.hide`
+    #[serde(default)]
     hide_folders: Vec<String>,
+    tree_separator: Option<String>,
+    #[serde(default)]
+    auto_reveal: bool,
+    #[serde(default)]
+    auto_save: bool,
     internal_clipboard: bool,
+    #[serde(default)]
+    clipboard: ClipboardBackend,
+    copy_whole_line: Option<bool>,
+    #[serde(default)]
+    eol_selection: bool,
     hover: HexColor,
     tree_width: u16,
     max_cursor_lines: u16,
+    undo_depth: Option<usize>,
+    undo_merge_ms: Option<u64>,
+    scroll_lines: Option<u16>,
+    wheel_lines: Option<u16>,
+    #[serde(default)]
+    show_whitespace: bool,
+    // space glyph, then tab glyph
+    whitespace_glyphs: Option<String>,
+    trailing_whitespace: Option<HexColor>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+struct Tree {
+    #[serde(default)]
+    hide: Vec<String>,
+}
+
+// how the system clipboard is reached
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardBackend {
+    #[default]
+    Commands,
+    // escape sequence, works over ssh
+    Osc52,
 }
 
 fn read_toml<'a>(
@@ -35,34 +83,38 @@ fn read_toml<'a>(
     custom: bool,
     path: &str,
     dst: &'a mut String,
-) -> &'a str {
+) -> Result<&'a str, String> {
     match fs::read_to_string(path).map(|s| *dst = s) {
-        Err(e) if custom => panic!("failed to load config file: {e:?}"),
-        Err(_) => default_value,
-        Ok(()) => dst.as_str(),
+        Err(e) if custom => Err(format!("failed to load config file: {e:?}")),
+        Err(_) => Ok(default_value),
+        Ok(()) => Ok(dst.as_str()),
     }
 }
 
-fn load() -> Config {
+fn read_config_str(dst: &mut String) -> Result<&str, String> {
     let path = env::var("HOP_CONFIG");
-    let mut tmp = String::new();
 
     let (custom, path) = match path.as_ref() {
         Ok(path) => (true, path.as_str()),
         Err(_) => (false, "~/.config/hop/config.toml"),
     };
 
-    let config_str = read_toml(
-        crate::DEFAULT_CONFIG,
-        custom,
-        path,
-        &mut tmp,
-    );
+    read_toml(crate::DEFAULT_CONFIG, custom, path, dst)
+}
+
+fn parse_config(config_str: &str) -> Result<Config, String> {
+    toml::from_str(config_str)
+        .map_err(|e| format!("failed to parse config: {:#?}", e.message()))
+}
 
-    match toml::from_str(&config_str) {
+fn load() -> Config {
+    let mut tmp = String::new();
+    let config_str = read_config_str(&mut tmp).unwrap_or_else(|e| panic!("{e}"));
+
+    match parse_config(config_str) {
         Ok(theme) => theme,
         Err(error) => {
-            alert!("failed to parse config: {:#?}", error.message());
+            alert!("{error}");
             let failure = "failed to parse fallback config file";
             toml::from_str(crate::DEFAULT_CONFIG).expect(failure)
         },
@@ -77,7 +129,12 @@ fn color(opt: Option<&HexColor>) -> Color {
 }
 
 fn config() -> &'static Config {
-    CONFIG.get_or_init(load)
+    if let Some(config) = *CONFIG.read().unwrap() {
+        return config;
+    }
+
+    let config = Box::leak(Box::new(load()));
+    *CONFIG.write().unwrap().get_or_insert(config)
 }
 
 pub fn init() {
@@ -92,43 +149,158 @@ pub fn max_cursor_lines() -> u16 {
     config().general.max_cursor_lines
 }
 
-pub fn syntax_file() -> SyntaxFile {
+pub fn undo_depth() -> usize {
+    config().general.undo_depth.unwrap_or(200)
+}
+
+pub fn undo_merge_window() -> Duration {
+    let ms = config().general.undo_merge_ms.unwrap_or(1000);
+    Duration::from_millis(ms)
+}
+
+// lines scrolled by page up / down, a full page by default
+pub fn scroll_lines() -> Option<u16> {
+    config().general.scroll_lines
+}
+
+// lines scrolled by each notch of the mouse wheel
+pub fn wheel_lines() -> u16 {
+    config().general.wheel_lines.unwrap_or(1)
+}
+
+fn read_syntax_str<'a>(config: &Config, dst: &'a mut String) -> Result<&'a str, String> {
     let default = (false, "~/.config/hop/syntax.toml");
-    let mut tmp = String::new();
 
-    let (custom, path) = config()
+    let (custom, path) = config
         .general
         .syntax_file
         .as_ref()
         .map(|p| (true, p.as_str()))
         .unwrap_or(default);
 
-    let syntax_str = read_toml(
-        crate::DEFAULT_SYNTAX,
-        custom,
-        path,
-        &mut tmp,
-    );
+    read_toml(crate::DEFAULT_SYNTAX, custom, path, dst)
+}
 
+pub fn syntax_file() -> SyntaxFile {
+    let mut tmp = String::new();
+    let syntax_str = read_syntax_str(config(), &mut tmp).unwrap_or_else(|e| panic!("{e}"));
     SyntaxFile::parse(syntax_str).unwrap_or_default()
 }
 
+// re-reads both files; on failure, the current config is kept
+pub fn reload() -> Option<SyntaxFile> {
+    let mut tmp = String::new();
+
+    let parsed = read_config_str(&mut tmp).and_then(parse_config);
+    let config = match parsed {
+        Ok(config) => config,
+        Err(error) => {
+            alert!("{error}");
+            return None;
+        },
+    };
+
+    let syntax_str = match read_syntax_str(&config, &mut tmp) {
+        Ok(syntax_str) => syntax_str,
+        Err(error) => {
+            alert!("{error}");
+            return None;
+        },
+    };
+
+    // alerts on failure
+    let syntaxes = SyntaxFile::parse(syntax_str).ok()?;
+
+    let config = Box::leak(Box::new(config));
+    *CONFIG.write().unwrap() = Some(config);
+    Some(syntaxes)
+}
+
 pub fn default_bg_color() -> Color {
     color(config().general.background.as_ref())
 }
 
+pub fn selection_color() -> Color {
+    match config().general.selection.as_ref() {
+        Some(hc) => color(Some(hc)),
+        None => Color::from((80, 80, 80)),
+    }
+}
+
+pub fn trailing_whitespace_color() -> Color {
+    match config().general.trailing_whitespace.as_ref() {
+        Some(hc) => color(Some(hc)),
+        None => Color::from((100, 40, 40)),
+    }
+}
+
+pub fn show_whitespace() -> bool {
+    config().general.show_whitespace
+}
+
+pub fn whitespace_glyphs() -> (char, char) {
+    let glyphs = config().general.whitespace_glyphs.as_deref();
+    let mut glyphs = glyphs.unwrap_or_default().chars();
+    let space = glyphs.next().unwrap_or('·');
+    let tab = glyphs.next().unwrap_or('→');
+    (space, tab)
+}
+
 pub fn hover_color() -> Color {
     color(Some(&config().general.hover))
 }
 
-pub fn hide_folder(folder: &str) -> bool {
-    config().general.hide_folders.iter().any(|f| f == folder)
+pub fn auto_save() -> bool {
+    config().general.auto_save
+}
+
+pub fn auto_reveal() -> bool {
+    config().general.auto_reveal
+}
+
+pub fn tree_separator() -> &'static str {
+    config().general.tree_separator.as_deref().unwrap_or("╌")
+}
+
+// dot-files and configured names, unless shown for now
+pub fn is_hidden(name: &str) -> bool {
+    if SHOW_HIDDEN.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    let config = config();
+    let mut names = config.tree.hide.iter().chain(&config.general.hide_folders);
+    name.starts_with('.') || names.any(|n| n == name)
+}
+
+pub fn toggle_hidden() {
+    SHOW_HIDDEN.fetch_xor(true, Ordering::Relaxed);
 }
 
 pub fn internal_clipboard() -> bool {
     config().general.internal_clipboard
 }
 
+pub fn clipboard_backend() -> ClipboardBackend {
+    config().general.clipboard
+}
+
+pub fn eol_selection() -> bool {
+    config().general.eol_selection
+}
+
+pub fn copy_whole_line() -> bool {
+    config().general.copy_whole_line.unwrap_or(true)
+}
+
+// rainbow brackets cycle through the colors
+// defined from `bracket-0`, if there are any
+pub fn bracket_colors() -> usize {
+    let syntax = &config().syntax;
+    let modes = crate::syntax::BRACKET_MODES.iter();
+    modes.take_while(|name| syntax.contains_key(**name)).count()
+}
+
 pub fn ansi_color(name: &str) -> Color {
     color(config().syntax.get(name))
 }
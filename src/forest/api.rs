@@ -4,6 +4,9 @@ use super::*;
 pub type Options<'a> = &'a mut Vec<MenuItem>;
 pub type TrunkId = Arc<str>;
 
+// path, match position, line contents
+pub type SearchHit = (String, (usize, usize), String);
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FileKey {
     trunk: Option<TrunkId>,
@@ -26,12 +29,46 @@ impl FileKey {
     pub fn path(&self) -> &str {
         &self.path
     }
+
+    // if this key is `old` or lies under it,
+    // returns its equivalent under `new`
+    pub fn rebase(&self, old: &FileKey, new: &FileKey) -> Option<Self> {
+        if self.trunk != old.trunk {
+            return None;
+        }
+
+        let rest = self.path.strip_prefix(&old.path)?;
+
+        if !rest.is_empty() && !old.path.ends_with('/') {
+            return None;
+        }
+
+        Some(Self {
+            trunk: new.trunk.clone(),
+            path: format!("{}{rest}", new.path),
+        })
+    }
+}
+
+// directories always come first
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SortMode {
+    #[default]
+    Name,
+    Extension,
+    // most recent first
+    Modified,
 }
 
 pub trait EntryApi {
     fn name(&self) -> &str;
     fn depth(&self) -> usize;
     fn is_dir(&self) -> bool;
+
+    // a char shown before the entry
+    fn status(&self) -> Option<char> {
+        None
+    }
 }
 
 #[enum_dispatch(Trunk)]
@@ -45,15 +82,24 @@ pub trait TrunkApi {
     fn file_text(&mut self, path: &str) -> Result<String, String>;
     fn save_file(&mut self, path: &str, text: &str) -> Result<(), String>;
 
-    fn search(&mut self, i: usize, text: &str) -> Vec<String> {
+    fn search(&mut self, i: usize, text: &str) -> Vec<SearchHit> {
         Vec::new()
     }
 
     fn open_dir(&mut self, i: usize);
     fn close_dir(&mut self, i: usize);
 
+    // reads open directories again
+    fn refresh(&mut self) {}
+
+    // open directories stay open
+    fn sort(&mut self, mode: SortMode) {}
+
     fn menu(&mut self, i: usize, options: Options) {}
-    fn act(&mut self, i: usize, action: MenuItem) {}
+    // gives the new key of a renamed entry
+    fn act(&mut self, i: usize, action: MenuItem) -> Option<FileKey> {
+        None
+    }
 
     fn reveal(&mut self, path: &str) -> Option<usize> {
         None
@@ -64,6 +110,10 @@ pub trait TrunkApi {
         None
     }
 
+    fn search_pos(&self, i: usize) -> Option<(usize, usize)> {
+        None
+    }
+
     fn is_dir_open(&self, mut i: usize) -> bool {
         let depth = self.get(i).depth();
         i += 1;
@@ -0,0 +1,68 @@
+use std::process::Command;
+
+// status chars of changed files, by path relative to a
+// local folder; folders outside of a git repo have none.
+#[derive(Default)]
+pub struct GitStatus {
+    files: Vec<(String, char)>,
+}
+
+fn git(dir: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+
+    match output.status.success() {
+        true => String::from_utf8(output.stdout).ok(),
+        false => None,
+    }
+}
+
+impl GitStatus {
+    pub fn load(dir: &str) -> Self {
+        let mut files = Vec::new();
+
+        // git paths are relative to the repository's root
+        let Some(prefix) = git(dir, &["rev-parse", "--show-prefix"]) else {
+            return Self { files };
+        };
+
+        let prefix = prefix.trim_end_matches('\n');
+        let status = git(dir, &["status", "--porcelain", "-z"]).unwrap_or_default();
+        let mut items = status.split('\0');
+
+        while let Some(item) = items.next() {
+            let mut chars = item.chars();
+            let (Some(x), Some(y), Some(' ')) = (chars.next(), chars.next(), chars.next()) else {
+                continue;
+            };
+
+            // renames and copies are followed by the original path
+            if matches!(x, 'R' | 'C') {
+                items.next();
+            }
+
+            let status = match (x, y) {
+                ('?', _) => 'U',
+                (x, ' ') => x,
+                (_, y) => y,
+            };
+
+            if let Some(path) = chars.as_str().strip_prefix(prefix) {
+                files.push((path.to_string(), status));
+            }
+        }
+
+        Self { files }
+    }
+
+    // directories take the status of a changed file below them
+    pub fn get(&self, path: &str) -> Option<char> {
+        let matches = |file: &str| match (path.ends_with('/'), file.ends_with('/')) {
+            (true, _) if file.starts_with(path) => true,
+            // untracked directories are listed as a whole
+            (_, true) => path.starts_with(file),
+            _ => path == file,
+        };
+
+        self.files.iter().find(|(file, _)| matches(file)).map(|(_, status)| *status)
+    }
+}
@@ -1,3 +1,4 @@
+use std::process::{Command, Stdio};
 use super::*;
 
 fn pop_dir_slash(text: &str) -> &str {
@@ -11,12 +12,15 @@ pub struct FsTrunk {
     prefix: String,
     entries: Vec<Entry>,
     walker: Walker,
+    sort: SortMode,
+    git: GitStatus,
 }
 
-#[derive(PartialEq, Eq)]
 struct Entry {
     name: String,
     depth: usize,
+    mtime: Option<SystemTime>,
+    status: Option<char>,
 }
 
 impl EntryApi for Entry {
@@ -31,6 +35,10 @@ impl EntryApi for Entry {
     fn is_dir(&self) -> bool {
         self.name.ends_with('/')
     }
+
+    fn status(&self) -> Option<char> {
+        self.status
+    }
 }
 
 impl FsTrunk {
@@ -52,13 +60,19 @@ impl FsTrunk {
         let base = Entry {
             name,
             depth: 0,
+            mtime: None,
+            status: None,
         };
 
+        let git = GitStatus::load(path);
+
         Self {
             id,
             prefix,
             entries: vec![base],
             walker: Walker::default(),
+            sort: SortMode::default(),
+            git,
         }
     }
 
@@ -67,9 +81,12 @@ impl FsTrunk {
         let parent = &self.entries[i];
         let inc_depth = parent.depth + 1;
 
+        // it was just created
         let entry = Entry {
             name,
             depth: inc_depth,
+            mtime: Some(SystemTime::now()),
+            status: None,
         };
 
         loop {
@@ -80,7 +97,7 @@ impl FsTrunk {
 
             match neighbor.depth.cmp(&inc_depth) {
                 Less => break,
-                Equal if neighbor >= &entry => break,
+                Equal if neighbor.cmp_by(&entry, self.sort).is_ge() => break,
                 _other => i = next,
             }
         }
@@ -116,7 +133,55 @@ impl FsTrunk {
         Ok(())
     }
 
-    fn try_act(&mut self, i: usize, action: MenuItem) -> io::Result<()> {
+    // note: should not use path walker here
+    fn duplicate(&mut self, i: usize) -> io::Result<()> {
+        let old_path = self.walker.result().to_string();
+        let entry = &self.entries[i];
+        let is_dir = entry.is_dir();
+        let old_name = pop_dir_slash(entry.name());
+        let parent = &old_path[..old_path.len() - entry.name().len()];
+
+        let mut default = copy_name(old_name, 1);
+        for n in 2.. {
+            if !fs::exists(format!("{parent}{default}"))? {
+                break;
+            }
+
+            default = copy_name(old_name, n);
+        }
+
+        let Some(mut new_name) = prompt!("Name of the copy (empty for {default:?}):") else {
+            return Ok(());
+        };
+
+        if new_name.is_empty() {
+            new_name = default;
+        }
+
+        let new_path = format!("{parent}{new_name}");
+
+        if fs::exists(&new_path)? {
+            let error = format!("{new_name:?} already exists");
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, error));
+        }
+
+        match is_dir {
+            true => copy_dir(&old_path, &new_path)?,
+            false => _ = fs::copy(&old_path, &new_path)?,
+        }
+
+        if is_dir {
+            new_name.push('/');
+        }
+
+        let depth = self.entries[i].depth;
+        let parent_i = (0..i).rev().find(|j| self.entries[*j].depth < depth).unwrap();
+        self.insert_entry(parent_i, new_name);
+
+        Ok(())
+    }
+
+    fn try_act(&mut self, i: usize, action: MenuItem) -> io::Result<Option<FileKey>> {
         use MenuItem::*;
 
         let old_path = self.walker.result();
@@ -128,7 +193,7 @@ impl FsTrunk {
         match action {
             Rename => {
                 let Some(mut new_name) = prompt!("New name for {old_name:?}:") else {
-                    return Ok(());
+                    return Ok(None);
                 };
 
                 utils::replace_last(&mut new_path, &old_name, &new_name);
@@ -138,11 +203,14 @@ impl FsTrunk {
                     new_name.push('/');
                 }
                 entry.name = new_name;
+                return Ok(Some(self.file_key(i)));
             },
             Delete => self.delete(i)?,
+            Duplicate => self.duplicate(i)?,
+            OpenExternal => open_external(old_path),
             NewDir => {
                 let Some(mut dir_name) = prompt!("Name of new directory in {old_name:?}:") else {
-                    return Ok(());
+                    return Ok(None);
                 };
 
                 dir_name.push('/');
@@ -152,7 +220,7 @@ impl FsTrunk {
             },
             NewFile => {
                 let Some(file_name) = prompt!("Name of new file in {old_name:?}:") else {
-                    return Ok(());
+                    return Ok(None);
                 };
 
                 new_path += &file_name;
@@ -162,7 +230,7 @@ impl FsTrunk {
             other => _ = alert!("Bad Code Path ({other:?})"),
         }
 
-        Ok(())
+        Ok(None)
     }
 
     fn prepare_path(&mut self, i: usize) {
@@ -199,14 +267,26 @@ impl TrunkApi for FsTrunk {
     }
 
     fn file_text(&mut self, path: &str) -> Result<String, String> {
-        fs::read_to_string(path).map_err(|e| format!("{e}"))
+        read(path)
     }
 
-    fn search(&mut self, i: usize, text: &str) -> Vec<String> {
+    fn search(&mut self, i: usize, text: &str) -> Vec<SearchHit> {
+        let mut hits = Vec::new();
+
+        if text.is_empty() {
+            return hits;
+        }
+
         self.prepare_path(i);
-        let mut paths = Vec::new();
-        recursive_search(&mut paths, self.walker.result(), text);
-        paths
+        let path = self.walker.result();
+
+        match self.entries[i].name.ends_with('/') {
+            true => recursive_search(&mut hits, path, text),
+            false => search_file(&mut hits, path, text),
+        }
+
+        hits.sort();
+        hits
     }
 
     fn save_file(&mut self, path: &str, text: &str) -> Result<(), String> {
@@ -230,7 +310,17 @@ impl TrunkApi for FsTrunk {
             self.entries.truncate(j);
         };
 
-        self.entries[j..].sort();
+        let sort = self.sort;
+        self.entries[j..].sort_by(|a, b| a.cmp_by(b, sort));
+
+        // git paths are relative to the root entry
+        let root_len = self.prefix.len() + self.entries[0].name.len();
+        let dir = &self.walker.result()[root_len..];
+
+        for entry in self.entries[j..].iter_mut() {
+            entry.status = self.git.get(&format!("{dir}{}", entry.name));
+        }
+
         self.entries.extend(suffix);
     }
 
@@ -241,19 +331,66 @@ impl TrunkApi for FsTrunk {
         self.entries.extend(suffix.into_iter().skip_while(crit));
     }
 
+    fn refresh(&mut self) {
+        let mut open_dirs = Vec::new();
+        self.git = GitStatus::load(&self.id);
+
+        for i in 0..self.entries.len() {
+            if self.is_dir_open(i) {
+                self.prepare_path(i);
+                open_dirs.push(self.walker.result().to_string());
+            }
+        }
+
+        self.close_dir(0);
+        let mut i = 0;
+
+        // opened directories push the next ones down
+        while i < self.entries.len() {
+            if self.entries[i].is_dir() {
+                self.prepare_path(i);
+
+                if open_dirs.iter().any(|p| p == self.walker.result()) {
+                    self.open_dir(i);
+                }
+            }
+
+            i += 1;
+        }
+    }
+
+    fn sort(&mut self, mode: SortMode) {
+        self.sort = mode;
+        let children = self.entries.split_off(1);
+        self.entries.extend(sort_subtrees(children, mode));
+    }
+
     fn menu(&mut self, i: usize, options: Options) {
         use MenuItem::*;
 
-        if self.get(i).is_dir() {
-            options.extend([Search, NewFile, NewDir]);
+        match self.get(i).is_dir() {
+            true => options.extend([Search, NewFile, NewDir]),
+            false => options.push(OpenExternal),
+        }
+
+        if i == 0 {
+            options.push(Sort);
         }
 
-        options.extend([Rename, Delete]);
+        options.extend([CopyPath, Rename, Delete]);
+
+        if i > 0 {
+            options.push(Duplicate);
+        }
     }
 
-    fn act(&mut self, i: usize, action: MenuItem) {
-        if let Err(error) = self.try_act(i, action) {
-            alert!("Failure: {error:?}");
+    fn act(&mut self, i: usize, action: MenuItem) -> Option<FileKey> {
+        match self.try_act(i, action) {
+            Ok(renamed) => renamed,
+            Err(error) => {
+                alert!("Failure: {error:?}");
+                None
+            },
         }
     }
 
@@ -274,7 +411,7 @@ fn read_dir(dir_path: &str, entries: &mut Vec<Entry>, depth: usize) -> io::Resul
             continue;
         };
 
-        let hidden = hide_folder(&name);
+        let hidden = is_hidden(&name);
 
         let (Ok(ft), false) = (item.file_type(), hidden) else {
             continue;
@@ -284,9 +421,13 @@ fn read_dir(dir_path: &str, entries: &mut Vec<Entry>, depth: usize) -> io::Resul
             name.push('/');
         }
 
+        let mtime = item.metadata().and_then(|m| m.modified()).ok();
+
         let entry = Entry {
             name,
             depth,
+            mtime,
+            status: None,
         };
 
         entries.push(entry);
@@ -297,6 +438,8 @@ fn read_dir(dir_path: &str, entries: &mut Vec<Entry>, depth: usize) -> io::Resul
         let entry = Entry {
             name: String::from("<empty>"),
             depth,
+            mtime: None,
+            status: None,
         };
 
         entries.push(entry);
@@ -305,28 +448,127 @@ fn read_dir(dir_path: &str, entries: &mut Vec<Entry>, depth: usize) -> io::Resul
     Ok(())
 }
 
+pub fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{e}"))
+}
+
+// None if the file is valid UTF-8 or can't be read
+pub fn read_lossy(path: &str) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+
+    match String::from_utf8(bytes) {
+        Ok(_) => None,
+        Err(error) => Some(String::from_utf8_lossy(error.as_bytes()).into_owned()),
+    }
+}
+
 pub fn save(path: &str, text: &str) -> Result<(), String> {
     fs::write(path, text).map_err(|e| format!("{e}"))
 }
 
-impl PartialOrd for Entry {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
+impl Entry {
+    fn extension(&self) -> &str {
+        match self.name.rsplit_once('.') {
+            Some(("", _)) | None => "",
+            Some((_, ext)) => ext,
+        }
+    }
+
+    // dirs < files
+    fn cmp_by(&self, other: &Self, mode: SortMode) -> cmp::Ordering {
+        let by_mode = match mode {
+            SortMode::Name => cmp::Ordering::Equal,
+            SortMode::Extension => self.extension().cmp(other.extension()),
+            SortMode::Modified => other.mtime.cmp(&self.mtime),
+        };
+
+        other
+            .is_dir()
+            .cmp(&self.is_dir())
+            .then(by_mode)
+            .then_with(|| self.name().cmp(other.name()))
+    }
+}
+
+// sorts sibling entries, each followed by its own open subtree
+fn sort_subtrees(entries: Vec<Entry>, mode: SortMode) -> Vec<Entry> {
+    let Some(depth) = entries.first().map(|e| e.depth) else {
+        return entries;
+    };
+
+    let mut groups: Vec<(Entry, Vec<Entry>)> = Vec::new();
+
+    for entry in entries {
+        match groups.last_mut() {
+            Some((_, children)) if entry.depth > depth => children.push(entry),
+            _ => groups.push((entry, Vec::new())),
+        }
+    }
+
+    groups.sort_by(|(a, _), (b, _)| a.cmp_by(b, mode));
+
+    let mut sorted = Vec::new();
+
+    for (entry, children) in groups {
+        sorted.push(entry);
+        sorted.extend(sort_subtrees(children, mode));
     }
+
+    sorted
 }
 
-// dirs < files
-impl Ord for Entry {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        match (self.is_dir(), other.is_dir()) {
-            (true, false) => cmp::Ordering::Less,
-            (false, true) => cmp::Ordering::Greater,
-            _ => self.name().cmp(other.name()),
+// with the desktop's default application
+fn open_external(path: &str) {
+    let candidates = ["xdg-open", "open"];
+
+    for command in candidates {
+        let mut cmd = Command::new(command);
+        cmd.arg(path).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+
+        let Ok(mut child) = cmd.spawn() else {
+            continue;
+        };
+
+        if child.wait().is_ok_and(|exit| exit.success()) {
+            return;
         }
     }
+
+    let ln1 = "failed to open this file with xdg-open or macOS's open.";
+    let ln2 = "please make sure one of these works.";
+    alert!("{ln1}\n{ln2}");
 }
 
-fn recursive_search(paths: &mut Vec<String>, dir_path: &str, text: &str) {
+// "name copy.ext", then "name copy 2.ext", ...
+fn copy_name(name: &str, n: usize) -> String {
+    let suffix = match n {
+        1 => String::from(" copy"),
+        n => format!(" copy {n}"),
+    };
+
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}{suffix}.{ext}"),
+        _ => format!("{name}{suffix}"),
+    }
+}
+
+fn copy_dir(from: &str, to: &str) -> io::Result<()> {
+    fs::create_dir(to)?;
+
+    for item in fs::read_dir(from)? {
+        let item = item?;
+        let dst = format!("{}/{}", pop_dir_slash(to), item.file_name().to_string_lossy());
+
+        match item.file_type()?.is_dir() {
+            true => copy_dir(&item.path().to_string_lossy(), &dst)?,
+            false => _ = fs::copy(item.path(), dst)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn recursive_search(hits: &mut Vec<SearchHit>, dir_path: &str, text: &str) {
     let Ok(dir_iter) = fs::read_dir(dir_path) else {
         return;
     };
@@ -346,23 +588,39 @@ fn recursive_search(paths: &mut Vec<String>, dir_path: &str, text: &str) {
             None => path_str,
         };
 
-        let hidden = hide_folder(&file_name);
+        let hidden = is_hidden(file_name);
 
         let (Ok(ft), false) = (item.file_type(), hidden) else {
             continue;
         };
 
-        if ft.is_dir() {
-            recursive_search(paths, path_str, text);
-            continue;
+        match ft.is_dir() {
+            true => recursive_search(hits, path_str, text),
+            false => search_file(hits, path_str, text),
         }
+    }
+}
 
-        let Ok(contents) = fs::read_to_string(&path_str) else {
-            continue;
-        };
+fn search_file(hits: &mut Vec<SearchHit>, path: &str, text: &str) {
+    // binary files are either invalid UTF-8 or contain null bytes
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
 
-        if contents.contains(text) {
-            paths.push(path_str.into());
-        }
+    if contents.contains('\0') {
+        return;
     }
+
+    let Some(offset) = contents.find(text) else {
+        return;
+    };
+
+    let before = &contents[..offset];
+    let y = before.matches('\n').count();
+
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let x = before[line_start..].chars().count();
+
+    let line = contents.lines().nth(y).unwrap_or("");
+    hits.push((path.into(), (x, y), line.trim().to_string()));
 }
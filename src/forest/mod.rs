@@ -1,37 +1,57 @@
 #![allow(unused_variables)]
 
 use std::{io, fs, cmp};
+use std::time::SystemTime;
 use std::fmt::Write;
 use std::sync::Arc;
 use std::mem::take;
 
 use crate::interface::menu::{MenuItem, context_menu};
 use crate::{alert, confirm, prompt};
-use crate::config::hide_folder;
+use crate::config::{is_hidden, tree_separator};
 
-pub use api::FileKey;
+pub use api::{FileKey, SortMode};
 
-use api::{Options, EntryApi, TrunkApi, AnchorApi, TrunkId, Trunk};
+use api::{Options, EntryApi, TrunkApi, AnchorApi, TrunkId, Trunk, SearchHit};
+use git_status::GitStatus;
 use search_fs::SearchTrunk;
 use local_fs::FsTrunk;
 use utils::Walker;
 
 mod api;
 mod utils;
+mod git_status;
 mod local_fs;
 mod search_fs;
 
 // syms: ▷▽▶▼;
 
+const SEPARATOR_LEN: usize = 256;
+const BINARY_WARNING: &str = "[BINARY FILE]";
+
+// lossy text had invalid bytes replaced, so it must not be saved
+pub struct FileText {
+    pub text: String,
+    pub lossy: bool,
+}
+
 pub struct FileBundle {
     pub key: FileKey,
-    pub text: String,
+    pub file: FileText,
     pub searched: Option<String>,
+    pub goto: Option<(usize, usize)>,
+}
+
+// outcomes of the context menu which concern tabs
+pub enum TreeEvent {
+    CopyPath(String),
+    Renamed(FileKey, FileKey),
 }
 
 pub struct Forest {
     trunks: Vec<Trunk>,
     scroll: usize,
+    sort: SortMode,
 }
 
 impl Forest {
@@ -39,11 +59,13 @@ impl Forest {
         Self {
             trunks: Vec::new(),
             scroll: 0,
+            sort: SortMode::default(),
         }
     }
 
     pub fn add_local_folder(&mut self, path: &str) -> TrunkId {
-        let trunk = FsTrunk::new(path);
+        let mut trunk = FsTrunk::new(path);
+        trunk.sort(self.sort);
         let id = trunk.id();
         self.trunks.push(trunk.into());
         id
@@ -77,13 +99,98 @@ impl Forest {
         None
     }
 
+    // trunks are separated by an extra row
+    fn rows(&self) -> usize {
+        self.len() + self.trunks.len().saturating_sub(1)
+    }
+
+    // Err(t) is the separator row before trunk t,
+    // or the end of the forest if t is out of bounds
+    fn row_to_index(&self, mut row: usize) -> Result<usize, usize> {
+        let mut index = 0;
+
+        for (t, trunk) in self.trunks.iter().enumerate() {
+            if t > 0 {
+                row = row.checked_sub(1).ok_or(t)?;
+            }
+
+            if row < trunk.len() {
+                return Ok(index + row);
+            }
+
+            row -= trunk.len();
+            index += trunk.len();
+        }
+
+        Err(self.trunks.len())
+    }
+
+    fn index_to_row(&self, mut index: usize) -> usize {
+        let mut row = index;
+
+        for trunk in self.trunks.iter() {
+            match index.checked_sub(trunk.len()) {
+                Some(next) => index = next,
+                None => break,
+            }
+
+            // separator
+            row += 1;
+        }
+
+        row
+    }
+
+    pub fn ensure_visible(&mut self, index: usize, height: usize) {
+        let row = self.index_to_row(index);
+
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + height {
+            self.scroll = (row + 1).saturating_sub(height);
+        }
+    }
+
+    pub fn check_index(&self, maybe_index: &mut Option<usize>) {
+        if maybe_index.is_some_and(|i| i >= self.len()) {
+            maybe_index.take();
+        }
+    }
+
+    fn separator(&self, buf: &mut String, t: usize) {
+        let sep = tree_separator();
+
+        if t >= self.trunks.len() || sep.is_empty() {
+            return;
+        }
+
+        let total = self.trunks.len();
+        let _ = write!(buf, "{sep}{sep} {}/{total} ", t + 1);
+
+        // cut by the interface
+        while buf.len() < SEPARATOR_LEN {
+            buf.push_str(sep);
+        }
+    }
+
     pub fn line(&self, buf: &mut String, index: u16) -> Option<usize> {
-        let mut i = index as usize + self.scroll;
+        let row = index as usize + self.scroll;
+
+        let mut i = match self.row_to_index(row) {
+            Ok(i) => i,
+            Err(t) => {
+                self.separator(buf, t);
+                return None;
+            },
+        };
+
+        let global = i;
         let trunk = self.trunk(&mut i)?;
         let entry = trunk.get(i);
         let is_dir = entry.is_dir();
         let name = entry.name();
         let indent = entry.depth() * 3;
+        let status = entry.status().unwrap_or(' ');
 
         let sym = match (is_dir, trunk.is_dir_open(i)) {
             (true, false) => '▷',
@@ -91,12 +198,12 @@ impl Forest {
             (false, _) => ' ',
         };
 
-        let _ = write!(buf, " {:1$}{sym} {name}", "", indent);
-        Some(index as usize + self.scroll)
+        let _ = write!(buf, "{status}{:1$}{sym} {name}", "", indent);
+        Some(global)
     }
 
     pub fn check_overscroll(&mut self) {
-        let max = self.len().saturating_sub(1);
+        let max = self.rows().saturating_sub(1);
 
         if self.scroll > max {
             self.scroll = max;
@@ -118,14 +225,49 @@ impl Forest {
             .find(|t| &*t.id() == trunk_id)
     }
 
+    // attaches the path to the first trunk containing it
+    pub fn key_for_path(&self, path: String) -> FileKey {
+        let contains = |t: &&Trunk| {
+            let rest = path.strip_prefix(&*t.id());
+            rest.is_some_and(|r| r.starts_with('/'))
+        };
+
+        match self.trunks.iter().find(contains) {
+            Some(trunk) => FileKey::new(trunk.id(), path),
+            None => FileKey::fallback(path),
+        }
+    }
+
+    pub fn has_trunk(&self, trunk_id: &str) -> bool {
+        self.trunks.iter().any(|t| &*t.id() == trunk_id)
+    }
+
+    // opens parent directories and returns the entry's global index;
+    // keys without a trunk are looked up in every trunk.
     pub fn reveal(&mut self, key: &FileKey) -> Option<usize> {
-        let id = key.trunk()?;
-        let trunk = self.trunk_by_id(id)?;
-        trunk.reveal(&key.path())
+        let mut offset = 0;
+
+        for trunk in self.trunks.iter_mut() {
+            let candidate = match key.trunk() {
+                Some(id) => &*trunk.id() == id,
+                None => true,
+            };
+
+            if candidate {
+                if let Some(i) = trunk.reveal(key.path()) {
+                    return Some(offset + i);
+                }
+            }
+
+            offset += trunk.len();
+        }
+
+        None
     }
 
     pub fn toggle_line(&mut self, line: usize) -> Option<FileBundle> {
-        self.toggle_index(line + self.scroll)
+        let i = self.row_to_index(line + self.scroll).ok()?;
+        self.toggle_index(i)
     }
 
     pub fn toggle_index(&mut self, mut i: usize) -> Option<FileBundle> {
@@ -140,40 +282,60 @@ impl Forest {
             None
         } else {
             let key = trunk.file_key(i);
-            let searched = trunk.search_term().into();
-            let text = self.open(&key)?;
+            let searched = trunk.search_term();
+            let goto = trunk.search_pos(i);
+            let file = self.open(&key)?;
 
             Some(FileBundle {
                 key,
-                text,
+                file,
                 searched,
+                goto,
             })
         }
     }
 
-    pub fn open(&mut self, key: &FileKey) -> Option<String> {
-        let id = key.trunk()?;
-        let trunk = self.trunk_by_id(id)?;
+    pub fn open(&mut self, key: &FileKey) -> Option<FileText> {
+        let result = match key.trunk() {
+            Some(id) => match self.trunk_by_id(id) {
+                Some(trunk) => trunk.file_text(key.path()),
+                None => Err("Failed to find file trunk".to_string()),
+            },
+            None => local_fs::read(key.path()),
+        };
 
-        match trunk.file_text(key.path()) {
-            Ok(text) => Some(text),
-            Err(error) => {
-                alert!("{}: {error}", key.path());
-                None
+        let path = key.path();
+
+        let text = match result {
+            Ok(text) if !text.contains('\0') => return Some(FileText { text, lossy: false }),
+            Ok(text) => text,
+            Err(error) => match local_fs::read_lossy(path) {
+                Some(text) => text,
+                None => {
+                    alert!("{path}: {error}");
+                    return None;
+                },
             },
+        };
+
+        match confirm!("{BINARY_WARNING}\n{path} is not a text file.\nOpen it read-only anyway?") {
+            true => Some(FileText { text, lossy: true }),
+            false => None,
         }
     }
 
-    pub fn save(&mut self, key: &FileKey, text: &str) -> Result<(), ()> {
-        let result = match key.trunk() {
+    pub fn try_save(&mut self, key: &FileKey, text: &str) -> Result<(), String> {
+        match key.trunk() {
             Some(id) => match self.trunk_by_id(id) {
                 Some(trunk) => trunk.save_file(key.path(), text),
                 None => Err("Failed to find file trunk".to_string()),
             },
             None => local_fs::save(key.path(), text),
-        };
+        }
+    }
 
-        if let Err(error) = result {
+    pub fn save(&mut self, key: &FileKey, text: &str) -> Result<(), ()> {
+        if let Err(error) = self.try_save(key, text) {
             alert!("{}: {error}", key.path());
             Err(())
         } else {
@@ -187,12 +349,11 @@ impl Forest {
         y: u16,
         entry: u16,
         is_in_use: F,
-    ) {
-        let j = entry as usize + self.scroll;
+    ) -> Option<TreeEvent> {
+        let j = self.row_to_index(entry as usize + self.scroll).ok()?;
+
         let mut i = j;
-        let Some(trunk) = self.trunk_mut(&mut i) else {
-            return;
-        };
+        let trunk = self.trunk_mut(&mut i)?;
 
         let mut options = Vec::with_capacity(8);
         trunk.menu(i, &mut options);
@@ -202,33 +363,59 @@ impl Forest {
         }
 
         if options.is_empty() {
-            return;
+            return None;
         }
 
-        if let Some(action) = context_menu(x, y, &options) {
-            if action == MenuItem::CloseTree {
-                self.remove_tree(j);
-                return;
-            }
+        let action = context_menu(x, y, &options)?;
 
-            if action == MenuItem::Search {
-                if let Some(text) = prompt!("{}", crate::SEARCH_PROMPT) {
-                    let id = trunk.id();
-                    self.search(id, i, text);
-                }
-                return;
-            }
+        if action == MenuItem::CloseTree {
+            self.remove_tree(j);
+            return None;
+        }
 
-            let forbid_use = [MenuItem::Rename, MenuItem::Delete];
-            let key = trunk.file_key(i);
-            let in_use = is_in_use(&key);
+        if action == MenuItem::Sort {
+            self.choose_sort(x, y);
+            return None;
+        }
 
-            if forbid_use.contains(&action) && in_use {
-                alert!("Not possible!\nAt least one of your tabs relies on this path.");
-                return;
+        if action == MenuItem::Search {
+            if let Some(text) = prompt!("{}", crate::SEARCH_PROMPT) {
+                let id = trunk.id();
+                self.search(id, i, text);
             }
+            return None;
+        }
+
+        let key = trunk.file_key(i);
+
+        if action == MenuItem::CopyPath {
+            return Some(TreeEvent::CopyPath(key.path().into()));
+        }
+
+        if action == MenuItem::Delete && is_in_use(&key) {
+            alert!("Not possible!\nAt least one of your tabs relies on this path.");
+            return None;
+        }
+
+        // open tabs must follow renamed files
+        let new_key = trunk.act(i, action)?;
+        Some(TreeEvent::Renamed(key, new_key))
+    }
+
+    fn choose_sort(&mut self, x: u16, y: u16) {
+        use MenuItem::*;
+
+        let mode = match context_menu(x, y, &[SortByName, SortByExtension, SortByDate]) {
+            Some(SortByName) => SortMode::Name,
+            Some(SortByExtension) => SortMode::Extension,
+            Some(SortByDate) => SortMode::Modified,
+            _ => return,
+        };
 
-            trunk.act(i, action);
+        self.sort = mode;
+
+        for trunk in self.trunks.iter_mut() {
+            trunk.sort(mode);
         }
     }
 
@@ -238,10 +425,10 @@ impl Forest {
             return;
         };
 
-        let paths = trunk.search(local, &text);
+        let hits = trunk.search(local, &text);
 
-        if !paths.is_empty() {
-            let trunk = SearchTrunk::new(id, paths, text);
+        if !hits.is_empty() {
+            let trunk = SearchTrunk::new(id, hits, text);
             self.trunks.push(trunk.into());
         } else {
             alert!("Not found in searched files");
@@ -249,16 +436,36 @@ impl Forest {
     }
 
     pub fn remove_tree(&mut self, mut offset: usize) {
+        let mut row = 0;
+
         for (i, trunk) in self.trunks.iter().enumerate() {
             if let Some(next) = offset.checked_sub(trunk.len()) {
                 offset = next;
+                row += trunk.len() + 1;
             } else {
+                // the trunk's rows, plus one separator if any
+                let multiple = self.trunks.len() > 1;
+                let removed = trunk.len() + multiple as usize;
+
+                // keep the rows below in place
+                if self.scroll >= row + removed {
+                    self.scroll -= removed;
+                } else if self.scroll > row {
+                    self.scroll = row;
+                }
+
                 self.trunks.remove(i);
                 break;
             }
         }
     }
 
+    pub fn refresh(&mut self) {
+        for trunk in self.trunks.iter_mut() {
+            trunk.refresh();
+        }
+    }
+
     pub fn enter_dir(&mut self, i: &mut usize) {
         let mut j = *i;
         let trunk = self.trunk_mut(&mut j).unwrap();
@@ -267,7 +474,7 @@ impl Forest {
             return;
         }
 
-        trunk.open_dir(*i);
+        trunk.open_dir(j);
         *i += 1;
     }
 
@@ -8,11 +8,14 @@ pub struct SearchTrunk {
 }
 
 impl SearchTrunk {
-    pub fn new(id: TrunkId, paths: Vec<String>, searched: String) -> Self {
-        let results = paths
+    pub fn new(id: TrunkId, hits: Vec<SearchHit>, searched: String) -> Self {
+        let results = hits
             .into_iter()
-            .map(|p| FileKey::new(id.clone(), p))
-            .map(|k| SearchResult(k))
+            .map(|(p, (x, y), line)| SearchResult {
+                key: FileKey::new(id.clone(), p),
+                pos: (x, y),
+                preview: Preview(format!("{}: {line}", y + 1)),
+            })
             .collect();
 
         Self {
@@ -24,13 +27,23 @@ impl SearchTrunk {
     }
 }
 
-pub struct SearchResult(FileKey);
+// each result takes two rows: the file, then its preview
+const RESULT_ROWS: usize = 2;
+
+pub struct SearchResult {
+    key: FileKey,
+    pos: (usize, usize),
+    preview: Preview,
+}
+
+// first matching line, shown under the file name
+pub struct Preview(String);
 
 impl EntryApi for SearchResult {
     fn name(&self) -> &str {
-        match self.0.path().rsplit_once('/') {
+        match self.key.path().rsplit_once('/') {
             Some((_, name)) => name,
-            None => self.0.path(),
+            None => self.key.path(),
         }
     }
 
@@ -38,6 +51,15 @@ impl EntryApi for SearchResult {
     fn is_dir(&self) -> bool { false }
 }
 
+impl EntryApi for Preview {
+    fn name(&self) -> &str {
+        &self.0
+    }
+
+    fn depth(&self) -> usize { 2 }
+    fn is_dir(&self) -> bool { false }
+}
+
 impl EntryApi for SearchTrunk {
     fn name(&self) -> &str {
         "[Search Results]"
@@ -55,19 +77,26 @@ impl TrunkApi for SearchTrunk {
     fn len(&self) -> usize {
         match self.folded {
             true => 1,
-            false => self.results.len() + 1,
+            false => self.results.len() * RESULT_ROWS + 1,
         }
     }
 
     fn get(&self, i: usize) -> &dyn EntryApi {
-        match i.checked_sub(1) {
-            Some(i) => &self.results[i],
-            None => self,
+        let Some(i) = i.checked_sub(1) else {
+            return self;
+        };
+
+        let result = &self.results[i / RESULT_ROWS];
+
+        match i % RESULT_ROWS {
+            0 => result,
+            _ => &result.preview,
         }
     }
 
     fn file_key(&mut self, i: usize) -> FileKey {
-        self.results[i.saturating_sub(1)].0.clone()
+        let i = i.saturating_sub(1) / RESULT_ROWS;
+        self.results[i].key.clone()
     }
 
     fn file_text(&mut self, _path: &str) -> Result<String, String> {
@@ -89,4 +118,9 @@ impl TrunkApi for SearchTrunk {
     fn search_term(&self) -> Option<String> {
         Some(self.searched.clone())
     }
+
+    fn search_pos(&self, i: usize) -> Option<(usize, usize)> {
+        let i = i.checked_sub(1)? / RESULT_ROWS;
+        Some(self.results[i].pos)
+    }
 }
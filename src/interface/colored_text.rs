@@ -1,13 +1,17 @@
 use crossterm::style::*;
+use unicode_width::UnicodeWidthChar;
 use std::fmt;
 
-use crate::config::{ansi_color, default_bg_color};
+use crate::config::{ansi_color, default_bg_color, selection_color};
+use crate::config::{trailing_whitespace_color, whitespace_glyphs};
 
 pub type ModeName = &'static str;
 pub type PartLen = usize;
 pub type Part = (ModeName, PartLen);
+// (first char, end char) of a wrapped line
+pub type Segment = (usize, Option<usize>);
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Selection {
     start: usize,
     len: usize,
@@ -26,6 +30,8 @@ pub struct ColoredText<'a> {
     tab_width_m1: usize,
     parts: &'a [Part],
     max_chars: usize,
+    segment: Segment,
+    whitespace: bool,
     text: &'a str,
 }
 
@@ -46,12 +52,38 @@ impl<'a> ColoredText<'a> {
             tab_width_m1,
             horizontal_scroll,
             max_chars: 0,
+            segment: (0, None),
+            whitespace: false,
         }
     }
 
     pub fn set_max(&mut self, max: usize) {
         self.max_chars = max;
     }
+
+    pub fn set_segment(&mut self, segment: Segment) {
+        self.segment = segment;
+    }
+
+    pub fn set_whitespace(&mut self, show: bool) {
+        self.whitespace = show;
+    }
+}
+
+// number of terminal cells taken by `c`;
+// control characters count as one cell
+pub fn char_width(c: char, tab_width_m1: usize) -> usize {
+    match c {
+        '\t' => tab_width_m1 + 1,
+        c => c.width().unwrap_or(1),
+    }
+}
+
+// marks a line which continues past the screen
+fn write_ellipsis(f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let dim1 = SetAttribute(Attribute::Dim);
+    let dim2 = SetAttribute(Attribute::NormalIntensity);
+    write!(f, "{}{dim1}…{dim2}", SetForegroundColor(Color::Reset))
 }
 
 fn write_cursor(f: &mut fmt::Formatter, c: char) -> Result<(), fmt::Error> {
@@ -62,7 +94,15 @@ fn write_cursor(f: &mut fmt::Formatter, c: char) -> Result<(), fmt::Error> {
 
 impl fmt::Display for ColoredText<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        let selected = Color::from((80, 80, 80));
+        let selected = selection_color();
+        let trailing_bg = trailing_whitespace_color();
+        let (space_glyph, tab_glyph) = whitespace_glyphs();
+
+        // chars from there are trailing whitespace
+        let trailing = match self.whitespace {
+            true => self.text.trim_end_matches([' ', '\t']).chars().count(),
+            false => usize::MAX,
+        };
 
         let mut skip_chars = self.horizontal_scroll;
         let mut iter_cursor = self.cursors.iter();
@@ -72,11 +112,12 @@ impl fmt::Display for ColoredText<'_> {
         let mut processed_chars = 0;
         let mut printed_chars = 0;
         let mut overflow = false;
+        let mut cut = false;
         let mut byte_offset = 0;
         let mut sel_end = None;
 
         if skip_chars > 0 {
-            write!(f, "{}…", SetForegroundColor(Color::Reset))?;
+            write_ellipsis(f)?;
             printed_chars = 1;
         }
 
@@ -89,14 +130,27 @@ impl fmt::Display for ColoredText<'_> {
             write!(f, "{}", SetForegroundColor(color))?;
 
             for mut new_char in text.chars() {
-                let mut added_chars = 1;
+                let width = char_width(new_char, self.tab_width_m1);
+                let mut added_chars = width;
+                let (first_char, end_char) = self.segment;
+                let hidden = processed_chars < first_char;
+
+                let glyph = match (self.whitespace, new_char) {
+                    (true, ' ') => Some(space_glyph),
+                    (true, '\t') => Some(tab_glyph),
+                    _ => None,
+                };
 
                 if new_char == '\t' {
-                    added_chars += self.tab_width_m1;
                     new_char = ' ';
                 }
 
-                overflow = printed_chars + added_chars >= self.max_chars;
+                cut = end_char == Some(processed_chars);
+                overflow = !hidden && printed_chars + added_chars >= self.max_chars;
+
+                if cut {
+                    break;
+                }
 
                 if overflow {
                     break;
@@ -115,18 +169,51 @@ impl fmt::Display for ColoredText<'_> {
                     }
                 }
 
-                if skip_chars < added_chars {
-                    added_chars = added_chars - skip_chars;
+                if processed_chars >= trailing && sel_end.is_none() {
+                    write!(f, "{}", SetBackgroundColor(trailing_bg))?;
+                }
+
+                // on a previous row of the wrapped line
+                if hidden {
+                    if Some(processed_chars) == cursor.copied() {
+                        cursor = iter_cursor.next();
+                    }
+
+                    processed_chars += 1;
+                    continue;
+                }
+
+                // zero-width chars combine with the previous one
+                if skip_chars < added_chars || skip_chars == 0 {
+                    added_chars -= skip_chars;
                     skip_chars = 0;
 
+                    // half of a wide char cannot be shown
+                    if added_chars < width {
+                        new_char = ' ';
+                    }
+
+                    let shown = match glyph {
+                        Some(glyph) if new_char == ' ' => glyph,
+                        _ => new_char,
+                    };
+
+                    if glyph.is_some() {
+                        write!(f, "{}", SetAttribute(Attribute::Dim))?;
+                    }
+
                     if Some(processed_chars) == cursor.copied() {
                         cursor = iter_cursor.next();
-                        write_cursor(f, new_char)?;
+                        write_cursor(f, shown)?;
                     } else {
-                        write!(f, "{new_char}")?;
+                        write!(f, "{shown}")?;
                     }
 
-                    if added_chars > 1 {
+                    if glyph.is_some() {
+                        write!(f, "{}", SetAttribute(Attribute::NormalIntensity))?;
+                    }
+
+                    if new_char == ' ' && added_chars > 1 {
                         let missing = added_chars - 1;
                         let _ = write!(f, "{:^1$}", "", missing);
                     }
@@ -139,15 +226,27 @@ impl fmt::Display for ColoredText<'_> {
                 processed_chars += 1;
             }
 
-            if overflow {
-                write!(f, "{}…", SetForegroundColor(Color::Reset))?;
+            if overflow | cut {
                 break;
             }
         }
 
+        let eol_selected = self
+            .selections
+            .iter()
+            .any(|s| (s.start..s.start + s.len).contains(&processed_chars));
+
         write!(f, "{}", SetBackgroundColor(default_bg_color()))?;
-        if self.cursors.contains(&byte_offset) & !overflow {
+        if cut {
+            // the line continues on the next row
+        } else if overflow {
+            // drawn outside of any selection
+            write_ellipsis(f)?;
+        } else if self.cursors.contains(&byte_offset) {
             write_cursor(f, ' ')?;
+        } else if eol_selected & (skip_chars == 0) {
+            write!(f, "{} ", SetBackgroundColor(selected))?;
+            write!(f, "{}", SetBackgroundColor(default_bg_color()))?;
         }
 
         Ok(())
@@ -1,27 +1,47 @@
 use super::*;
+use crate::tab::CaseMode;
+use crate::config::{scroll_lines, wheel_lines};
 
 #[derive(Copy, Clone, Debug)]
 pub enum UserInput {
     Quit(bool),
     Save,
+    SaveAll,
+    Reload,
+    ReloadConfig,
+    RecentFiles,
     CloseTab(Option<u16>),
     NextTab(bool),
     Insert(char),
     InsertTab,
+    Dedent,
+    ToggleComment,
+    TransformCase(CaseMode),
+    Align,
+    Surround,
+    Increment,
+    Decrement,
     CarriageReturn,
     CodeSeek(u16, u16, bool),
     CodeDrag(u16, u16),
+    CodeExtend(u16, u16),
+    BlockDrag(u16, u16),
     Reveal,
+    FocusTree,
     TreeClick(u16),
     CursorClick(u16),
     TreeHover(u16),
     TabHover(u16),
+    ToolbarHover(u16),
     CursorHover(u16),
     ClearHover,
     TabClick(u16),
     Backspace(bool),
+    DeleteWord(bool),
     Find,
+    Replace,
     Paste,
+    PasteFromHistory,
     Copy,
     Cut,
     Undo,
@@ -30,12 +50,71 @@ pub enum UserInput {
     SeekLineEnd(bool),
     PanelResize(bool),
     Scroll(isize),
+    HorizontalScroll(isize),
+    CenterView,
     Resize(u16, u16),
+    FocusGained,
+    FocusLost,
     HorizontalJump(isize, bool),
+    WordJump(bool, bool),
+    AddCursorVertical(bool),
+    CursorsAtLineEdges(bool),
     VerticalJump(isize, bool),
     ContextMenu(Location, u16, u16),
     AutoSelect,
     SelectAll,
+    SplitSelection,
+    SelectInside(bool),
+    SelectBlock,
+    MoveLines(bool),
+    ToggleWrap,
+    ToggleWhitespace,
+    ToggleHidden,
+    RefreshTree,
+    ToggleFold,
+    FoldClick(u16),
+    LineSelect(u16, bool),
+    ToggleBookmark,
+    ToggleOvertype,
+    JumpBack,
+    JumpForward,
+    NextBookmark,
+    PrevBookmark,
+    NoOp,
+}
+
+impl UserInput {
+    // refused by read-only tabs
+    pub fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::Paste
+                | Self::PasteFromHistory
+                | Self::Cut
+                | Self::InsertTab
+                | Self::Dedent
+                | Self::Replace
+                | Self::ToggleComment
+                | Self::TransformCase(_)
+                | Self::Align
+                | Self::Surround
+                | Self::Increment
+                | Self::Decrement
+                | Self::MoveLines(_)
+                | Self::Backspace(_)
+                | Self::DeleteWord(_)
+                | Self::Undo
+                | Self::Redo
+        )
+    }
+}
+
+pub enum SearchEvent {
+    Edited,
+    Next,
+    Previous,
+    Accept,
+    Cancel,
     NoOp,
 }
 
@@ -51,11 +130,13 @@ pub enum ResizeEvent {
 pub enum Location {
     PanelSep,
     Menu,
+    Toolbar(u16),
     MenuEdge,
     TreeRow(u16),
     Cursors(u16),
     Tab(u16),
-    LineNo(u16),
+    StatusBar,
+    LineNo(u16, u16),
     Code(u16, u16),
 }
 
@@ -68,7 +149,9 @@ impl Interface {
         if x == self.panel_width {
             Location::PanelSep
         } else if x < self.panel_width {
-            if y < MENU_HEIGHT {
+            if y == TOOLBAR_Y {
+                Location::Toolbar(x)
+            } else if y < MENU_HEIGHT {
                 Location::Menu
             } else if y == cursors_y {
                 Location::MenuEdge
@@ -81,8 +164,10 @@ impl Interface {
             }
         } else if y < 3 {
             Location::Tab(x - self.panel_width - 1)
+        } else if y >= self.height.saturating_sub(STATUS_HEIGHT) {
+            Location::StatusBar
         } else if x < code_x {
-            Location::LineNo(y - 3)
+            Location::LineNo(x - self.panel_width - 1, y - 3)
         } else {
             Location::Code(x - code_x, y - 3)
         }
@@ -90,6 +175,8 @@ impl Interface {
 
     pub fn read_event(&self, num_cursors: u16) -> UserInput {
         let code_height = self.code_height() as isize;
+        let page = scroll_lines().map_or(code_height, |n| n as isize);
+        let half_page = (code_height / 2).max(1);
         let event = read().unwrap();
 
         let fallback = || {
@@ -101,24 +188,47 @@ impl Interface {
             Event::Key(e) if e.is_release() => UserInput::NoOp,
             Event::Key(e) => {
                 let shift = e.modifiers.contains(KeyModifiers::SHIFT);
+                let alt = e.modifiers.contains(KeyModifiers::ALT);
 
                 if e.modifiers.contains(KeyModifiers::CONTROL) {
                     match e.code {
-                        KeyCode::Right => UserInput::HorizontalJump(10, shift),
-                        KeyCode::Left => UserInput::HorizontalJump(-10, shift),
+                        KeyCode::Right => UserInput::WordJump(true, shift),
+                        KeyCode::Left => UserInput::WordJump(false, shift),
+                        KeyCode::Backspace => UserInput::DeleteWord(false),
+                        KeyCode::Delete => UserInput::DeleteWord(true),
                         KeyCode::Char('d') => UserInput::AutoSelect,
                         KeyCode::Char('a') => UserInput::SelectAll,
+                        KeyCode::Char('l') => UserInput::SplitSelection,
+                        KeyCode::Char('j' | 'J') => UserInput::SelectInside(shift),
+                        KeyCode::Char('b') => UserInput::SelectBlock,
+                        // most terminals send Ctrl + 7 for Ctrl + /
+                        KeyCode::Char('/' | '7') => UserInput::ToggleComment,
+                        KeyCode::Char('u' | 'U') if alt => UserInput::TransformCase(CaseMode::Toggle),
+                        KeyCode::Char('u' | 'U') if shift => UserInput::TransformCase(CaseMode::Lower),
+                        KeyCode::Char('u') => UserInput::TransformCase(CaseMode::Upper),
                         KeyCode::Char('w') => UserInput::CloseTab(None),
                         KeyCode::Char('o') => UserInput::Reveal,
+                        KeyCode::Char('e') => UserInput::RecentFiles,
+                        KeyCode::Char('t') => UserInput::FocusTree,
                         KeyCode::Char('q') => UserInput::Quit(true),
+                        KeyCode::Char('s' | 'S') if shift => UserInput::SaveAll,
                         KeyCode::Char('s') => UserInput::Save,
+                        KeyCode::Char('r' | 'R') if shift => UserInput::ReloadConfig,
+                        KeyCode::Char('r') => UserInput::Reload,
                         KeyCode::Char('z') => UserInput::Undo,
                         KeyCode::Char('y') => UserInput::Redo,
                         KeyCode::Char('f') => UserInput::Find,
+                        KeyCode::Char('h') => UserInput::Replace,
+                        KeyCode::Char('v') if alt => UserInput::PasteFromHistory,
                         KeyCode::Char('v') => UserInput::Paste,
                         KeyCode::Char('c') => UserInput::Copy,
                         KeyCode::Char('x') => UserInput::Cut,
                         KeyCode::Home => UserInput::PanelResize(!shift),
+                        KeyCode::F(2) => UserInput::ToggleBookmark,
+                        KeyCode::Down if alt => UserInput::AddCursorVertical(false),
+                        KeyCode::Up if alt => UserInput::AddCursorVertical(true),
+                        KeyCode::Down if shift => UserInput::Decrement,
+                        KeyCode::Up if shift => UserInput::Increment,
                         KeyCode::Down => UserInput::Scroll(1),
                         KeyCode::Up => UserInput::Scroll(-1),
                         _ => fallback(),
@@ -127,19 +237,39 @@ impl Interface {
                     match e.code {
                         KeyCode::PageDown if shift => UserInput::NextTab(true),
                         KeyCode::PageUp if shift => UserInput::NextTab(false),
-                        KeyCode::PageDown => UserInput::Scroll(code_height),
-                        KeyCode::PageUp => UserInput::Scroll(-code_height),
+                        KeyCode::PageDown if alt => UserInput::Scroll(half_page),
+                        KeyCode::PageUp if alt => UserInput::Scroll(-half_page),
+                        KeyCode::PageDown => UserInput::Scroll(page),
+                        KeyCode::PageUp => UserInput::Scroll(-page),
+                        KeyCode::Right if alt => UserInput::JumpForward,
+                        KeyCode::Left if alt => UserInput::JumpBack,
                         KeyCode::Right => UserInput::HorizontalJump(1, shift),
                         KeyCode::Left => UserInput::HorizontalJump(-1, shift),
+                        KeyCode::Char('i' | 'I') if alt & shift => UserInput::CursorsAtLineEdges(true),
+                        KeyCode::Char('a' | 'A') if alt & shift => UserInput::CursorsAtLineEdges(false),
+                        KeyCode::Char('a') if alt => UserInput::Align,
+                        KeyCode::Char('s') if alt => UserInput::Surround,
+                        KeyCode::Char('z') if alt => UserInput::ToggleWrap,
+                        KeyCode::Char('w') if alt => UserInput::ToggleWhitespace,
+                        KeyCode::Char('h') if alt => UserInput::ToggleHidden,
+                        KeyCode::Char('f') if alt => UserInput::ToggleFold,
+                        KeyCode::Char('l') if alt => UserInput::CenterView,
+                        KeyCode::F(2) if shift => UserInput::PrevBookmark,
+                        KeyCode::F(2) => UserInput::NextBookmark,
+                        KeyCode::F(5) => UserInput::RefreshTree,
+                        KeyCode::Down if alt => UserInput::MoveLines(false),
+                        KeyCode::Up if alt => UserInput::MoveLines(true),
                         KeyCode::Down => UserInput::VerticalJump(1, shift),
                         KeyCode::Up => UserInput::VerticalJump(-1, shift),
                         KeyCode::Backspace => UserInput::Backspace(false),
                         KeyCode::Delete => UserInput::Backspace(true),
+                        KeyCode::Insert => UserInput::ToggleOvertype,
                         KeyCode::Enter => UserInput::CarriageReturn,
                         KeyCode::Char(c) => UserInput::Insert(c),
                         KeyCode::Home => UserInput::SeekLineStart(shift),
                         KeyCode::End => UserInput::SeekLineEnd(shift),
                         KeyCode::Tab => UserInput::InsertTab,
+                        KeyCode::BackTab => UserInput::Dedent,
                         KeyCode::Esc => UserInput::Quit(false),
                         _ => fallback(),
                     }
@@ -148,6 +278,9 @@ impl Interface {
             Event::Mouse(e) => {
                 use {MouseEventKind::*, MouseButton::*};
                 let ctrl = e.modifiers.contains(KeyModifiers::CONTROL);
+                let alt = e.modifiers.contains(KeyModifiers::ALT);
+                let shift = e.modifiers.contains(KeyModifiers::SHIFT);
+                let wheel = wheel_lines() as isize;
                 let pos = self.cursor_pos(e.column, e.row, num_cursors);
                 let context_menu = UserInput::ContextMenu(pos, e.column, e.row);
 
@@ -158,17 +291,23 @@ impl Interface {
 
                 match pos {
                     Location::Code(x, y) => match e.kind {
-                        ScrollDown => UserInput::Scroll(1),
-                        ScrollUp => UserInput::Scroll(-1),
+                        ScrollDown if shift => UserInput::HorizontalScroll(wheel),
+                        ScrollUp if shift => UserInput::HorizontalScroll(-wheel),
+                        ScrollRight => UserInput::HorizontalScroll(wheel),
+                        ScrollLeft => UserInput::HorizontalScroll(-wheel),
+                        ScrollDown => UserInput::Scroll(wheel),
+                        ScrollUp => UserInput::Scroll(-wheel),
+                        Down(Left) if shift => UserInput::CodeExtend(x, y),
                         Down(Left) => UserInput::CodeSeek(x, y, ctrl),
                         Up(_) => UserInput::NoOp,
+                        Drag(Left) if alt => UserInput::BlockDrag(x, y),
                         Drag(Left) => UserInput::CodeDrag(x, y),
                         Moved => UserInput::ClearHover,
                         _ => mouse_fallback(),
                     },
                     Location::TreeRow(y) => match e.kind {
-                        ScrollDown => UserInput::Scroll(1),
-                        ScrollUp => UserInput::Scroll(-1),
+                        ScrollDown => UserInput::Scroll(wheel),
+                        ScrollUp => UserInput::Scroll(-wheel),
                         Up(_) => UserInput::NoOp,
                         Down(Left) => UserInput::TreeClick(y),
                         Down(Right) => context_menu,
@@ -179,8 +318,8 @@ impl Interface {
                     Location::Cursors(y) => match e.kind {
                         Up(_) => UserInput::NoOp,
                         Down(Left) => UserInput::CursorClick(y),
-                        ScrollDown => UserInput::Scroll(1),
-                        ScrollUp => UserInput::Scroll(-1),
+                        ScrollDown => UserInput::Scroll(wheel),
+                        ScrollUp => UserInput::Scroll(-wheel),
                         Moved => UserInput::CursorHover(y),
                         Drag(Left) => UserInput::NoOp,
                         _ => mouse_fallback(),
@@ -194,10 +333,13 @@ impl Interface {
                         Drag(Left) => UserInput::NoOp,
                         _ => mouse_fallback(),
                     },
-                    Location::LineNo(_y) => match e.kind {
+                    Location::LineNo(x, y) => match e.kind {
+                        // the column of fold marks
+                        Down(Left) if x as usize >= LN_WIDTH => UserInput::FoldClick(y),
+                        Down(Left) => UserInput::LineSelect(y, shift),
                         Up(_) => UserInput::NoOp,
                         Moved => UserInput::ClearHover,
-                        Drag(Left) => UserInput::NoOp,
+                        Drag(Left) => UserInput::LineSelect(y, true),
                         _ => mouse_fallback(),
                     },
                     Location::PanelSep => match e.kind {
@@ -206,6 +348,21 @@ impl Interface {
                         Up(_) => UserInput::NoOp,
                         _ => mouse_fallback(),
                     },
+                    Location::StatusBar => match e.kind {
+                        Up(_) => UserInput::NoOp,
+                        Moved => UserInput::ClearHover,
+                        _ => mouse_fallback(),
+                    },
+                    Location::Toolbar(x) => match e.kind {
+                        Up(_) => UserInput::NoOp,
+                        Down(Left) => match self.toolbar_cell(x) {
+                            Some(i) => TOOLBAR[i].1,
+                            None => UserInput::NoOp,
+                        },
+                        Moved => UserInput::ToolbarHover(x),
+                        Drag(Left) => UserInput::NoOp,
+                        _ => mouse_fallback(),
+                    },
                     Location::MenuEdge => match e.kind {
                         Up(_) => UserInput::NoOp,
                         Moved => UserInput::ClearHover,
@@ -219,6 +376,8 @@ impl Interface {
                 }
             },
             Event::Resize(w, h) => UserInput::Resize(*w, *h),
+            Event::FocusGained => UserInput::FocusGained,
+            Event::FocusLost => UserInput::FocusLost,
             _other => fallback(),
         }
     }
@@ -231,6 +390,36 @@ impl Interface {
         self.panel_width = op(self.panel_width);
     }
 
+    pub fn search_event(&mut self, needle: &mut String) -> SearchEvent {
+        const SHORTCUT: KeyModifiers = KeyModifiers::CONTROL.union(KeyModifiers::ALT);
+
+        match read().unwrap() {
+            Event::Key(e) if e.is_release() => SearchEvent::NoOp,
+            Event::Key(e) => match e.code {
+                KeyCode::Enter | KeyCode::Down => SearchEvent::Next,
+                KeyCode::Up => SearchEvent::Previous,
+                KeyCode::Tab => SearchEvent::Accept,
+                KeyCode::Esc => SearchEvent::Cancel,
+                KeyCode::Backspace => match needle.pop() {
+                    Some(_) => SearchEvent::Edited,
+                    None => SearchEvent::NoOp,
+                },
+                // unbound shortcuts don't type anything
+                KeyCode::Char(_) if e.modifiers.intersects(SHORTCUT) => SearchEvent::NoOp,
+                KeyCode::Char(c) => {
+                    needle.push(c);
+                    SearchEvent::Edited
+                },
+                _ => SearchEvent::NoOp,
+            },
+            Event::Resize(w, h) => {
+                self.resize(w, h);
+                SearchEvent::NoOp
+            },
+            _other => SearchEvent::NoOp,
+        }
+    }
+
     pub fn panel_resize_event(&self) -> ResizeEvent {
         match read().unwrap() {
             Event::Key(e) => {
@@ -4,13 +4,27 @@ use super::*;
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MenuItem {
     CloseTab,
+    CloseOthers,
+    CloseRight,
+    Pin,
+    ReadOnly,
     NewFile,
     Syntax,
     IndentMode,
+    Retab,
+    LineEndings,
     NewDir,
     Rename,
     Delete,
+    Duplicate,
+    OpenExternal,
+    CopyPath,
     Search,
+    Sort,
+    SortByName,
+    SortByExtension,
+    SortByDate,
+    Wrap,
     CloseTree,
 }
 
@@ -20,14 +34,28 @@ impl Deref for MenuItem {
     fn deref(&self) -> &Self::Target {
         match self {
             Self::IndentMode => "Indent Mode",
+            Self::Retab => "Retab",
+            Self::LineEndings => "Line Endings",
             Self::CloseTab => "Close Tab",
+            Self::CloseOthers => "Close Others",
+            Self::CloseRight => "Close Right",
+            Self::Pin => "Pin / Unpin",
+            Self::ReadOnly => "Read-Only",
             Self::NewFile => "New File",
             Self::CloseTree => "Close",
             Self::NewDir => "New Dir.",
             Self::Syntax => "Syntax",
             Self::Rename => "Rename",
             Self::Delete => "Delete",
+            Self::Duplicate => "Duplicate",
+            Self::OpenExternal => "External App",
+            Self::CopyPath => "Copy Path",
             Self::Search => "Search",
+            Self::Sort => "Sort By",
+            Self::SortByName => "Name",
+            Self::SortByExtension => "Extension",
+            Self::SortByDate => "Modified",
+            Self::Wrap => "Word Wrap",
         }
     }
 }
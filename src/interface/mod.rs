@@ -8,6 +8,7 @@ use colored_text::ColoredText;
 
 use crate::config::{ansi_color, tree_width, default_bg_color, hover_color};
 use crate::tab::TabList;
+use input::UserInput;
 
 pub mod colored_text;
 pub mod popup;
@@ -15,8 +16,20 @@ pub mod input;
 pub mod menu;
 
 const TABS_HEIGHT: u16 = 3;
+const STATUS_HEIGHT: u16 = 1;
 const MENU_HEIGHT: u16 = 4;
 const LN_WIDTH: usize = 4;
+const TOOLBAR_Y: u16 = 1;
+// limits when resizing the panel by hand
+const MIN_PANEL_WIDTH: u16 = 8;
+const MIN_TABS_WIDTH: u16 = 16;
+
+const TOOLBAR: [(&str, UserInput); 4] = [
+    (" Save ", UserInput::Save),
+    (" Find ", UserInput::Find),
+    (" Undo ", UserInput::Undo),
+    (" Redo ", UserInput::Redo),
+];
 
 static DIRTY: AtomicBool = AtomicBool::new(true);
 
@@ -34,6 +47,7 @@ impl Interface {
         queue!(stdout, SavePosition).unwrap();
         queue!(stdout, EnterAlternateScreen).unwrap();
         queue!(stdout, EnableMouseCapture).unwrap();
+        queue!(stdout, EnableFocusChange).unwrap();
         queue!(stdout, SetBackgroundColor(default_bg_color())).unwrap();
         queue!(stdout, Hide).unwrap();
         let _ = enable_raw_mode();
@@ -64,12 +78,32 @@ impl Interface {
         restore_term();
     }
 
+    pub fn panel_width(&self) -> u16 {
+        self.panel_width
+    }
+
+    // only toggling the panel can hide it
+    pub fn clamp_panel_width(&mut self) {
+        let max = self.width.saturating_sub(MIN_TABS_WIDTH + 1);
+        self.panel_width = self.panel_width.clamp(MIN_PANEL_WIDTH, max.max(MIN_PANEL_WIDTH));
+    }
+
+    // readout next to the divider, while resizing
+    pub fn draw_panel_width(&mut self) {
+        let text = format!(" {} ", self.panel_width);
+
+        queue!(self.stdout, SetBackgroundColor(hover_color())).unwrap();
+        self.write_text(self.panel_width + 1, TABS_HEIGHT, text);
+        queue!(self.stdout, SetBackgroundColor(default_bg_color())).unwrap();
+        let _ = self.stdout.flush();
+    }
+
     pub fn tree_height(&self) -> u16 {
         self.height.saturating_sub(MENU_HEIGHT + 1)
     }
 
     pub fn code_height(&self) -> u16 {
-        self.height.saturating_sub(TABS_HEIGHT)
+        self.height.saturating_sub(TABS_HEIGHT + STATUS_HEIGHT)
     }
 
     fn tabs_width(&self) -> usize {
@@ -123,6 +157,51 @@ impl Interface {
         let _ = self.stdout.flush();
     }
 
+    fn toolbar_cell(&self, x: u16) -> Option<usize> {
+        let mut start = 1;
+
+        for (i, (label, _)) in TOOLBAR.iter().enumerate() {
+            let end = start + label.len() as u16;
+
+            if end > self.panel_width {
+                break;
+            }
+
+            if (start..end).contains(&x) {
+                return Some(i);
+            }
+
+            start = end;
+        }
+
+        None
+    }
+
+    pub fn set_toolbar(&mut self, hover_pos: Option<u16>) {
+        let hovered = hover_pos.and_then(|x| self.toolbar_cell(x));
+        let mut x = 1;
+
+        for (i, (label, _)) in TOOLBAR.iter().enumerate() {
+            let end = x + label.len() as u16;
+
+            if end > self.panel_width {
+                break;
+            }
+
+            let bg_color = match hovered == Some(i) {
+                true => hover_color(),
+                false => default_bg_color(),
+            };
+
+            queue!(self.stdout, SetBackgroundColor(bg_color)).unwrap();
+            self.write_text(x, TOOLBAR_Y, label);
+            x = end;
+        }
+
+        queue!(self.stdout, SetBackgroundColor(default_bg_color())).unwrap();
+        let _ = self.stdout.flush();
+    }
+
     pub fn set_tree_row(
         &mut self,
         selected: bool,
@@ -162,7 +241,13 @@ impl Interface {
         let _ = write!(self.stdout, "{:─^1$}┤", text, width);
     }
 
-    pub fn set_code_row(&mut self, index: u16, line_no: Option<usize>, mut text: ColoredText) {
+    pub fn set_code_row(
+        &mut self,
+        index: u16,
+        line_no: Option<usize>,
+        mark: char,
+        mut text: ColoredText,
+    ) {
         let line_no: &dyn fmt::Display = match line_no.as_ref() {
             Some(n) => n,
             None => &"",
@@ -170,7 +255,7 @@ impl Interface {
 
         let mut buf = take(&mut self.str_buf);
         buf.clear();
-        let _ = write!(&mut buf, "{:1$} ", line_no, LN_WIDTH);
+        let _ = write!(&mut buf, "{:1$}{mark}", line_no, LN_WIDTH);
 
         let y = TABS_HEIGHT + index;
         let mut x = self.panel_width + 1;
@@ -185,6 +270,20 @@ impl Interface {
         let _ = self.stdout.flush();
     }
 
+    pub fn set_status(&mut self, text: &str) {
+        let x = self.panel_width + 1;
+        let y = self.height.saturating_sub(STATUS_HEIGHT);
+        let max = self.tabs_width();
+        let (cut, chars) = cut_len(text, max);
+
+        queue!(self.stdout, SetBackgroundColor(hover_color())).unwrap();
+        self.write_text(x, y, &text[..cut]);
+        let _ = write!(self.stdout, "{:1$}", "", max - chars);
+
+        queue!(self.stdout, SetBackgroundColor(default_bg_color())).unwrap();
+        let _ = self.stdout.flush();
+    }
+
     pub fn set_tab_list(
         &mut self,
         hover_pos: Option<u16>,
@@ -287,6 +386,7 @@ pub fn restore_term() {
     let _ = disable_raw_mode();
     queue!(stdout, SetBackgroundColor(Color::Reset)).unwrap();
     queue!(stdout, DisableMouseCapture).unwrap();
+    queue!(stdout, DisableFocusChange).unwrap();
     queue!(stdout, LeaveAlternateScreen).unwrap();
     queue!(stdout, RestorePosition).unwrap();
     queue!(stdout, Show).unwrap();
@@ -1,12 +1,15 @@
 use interface::colored_text::{ColoredText, Part as TextPart, Selection};
-use interface::input::{UserInput, ResizeEvent, Location};
+use interface::input::{UserInput, ResizeEvent, SearchEvent, Location};
 use interface::menu::{MenuItem, context_menu};
 use interface::{Interface, restore_term};
-use forest::{Forest, FileKey};
+use forest::{Forest, FileKey, FileText, TreeEvent};
 use tab::{TabMap, TabList};
 use syntax::SyntaxFile;
+use session::{Session, TabState};
 
 use std::{env, fs, panic, backtrace};
+use std::mem::take;
+use std::path::{self, Path};
 use std::fmt::Write;
 
 mod interface;
@@ -14,9 +17,14 @@ mod config;
 mod syntax;
 mod forest;
 mod tab;
+mod session;
 
 const CONFIRM_QUIT: &str = "[UNSAVED FILES]\nReally Quit? Some files have unsaved edits!";
+const CONFIRM_RELOAD: &str = "[UNSAVED FILE]\nReally reload this file? Its unsaved edits will be lost!";
+const READ_ONLY_ALERT: &str = "This tab is read-only; unlock it from its context menu.";
+const LOSSY_ALERT: &str = "Cannot save: invalid bytes of this file were replaced when opening it.";
 const SEARCH_PROMPT: &str = "Please input the text to look for:";
+const REPLACE_PROMPT: &str = "Please input the replacement text:";
 
 const DEFAULT_CONFIG: &str = include_str!("../assets/config.toml");
 const DEFAULT_SYNTAX: &str = include_str!("../assets/syntax.toml");
@@ -26,11 +34,38 @@ const DEFAULT_SYNTAX: &str = include_str!("../assets/syntax.toml");
 // in the future, this could be optimized easily.
 const FOR_CURSORS: bool = true;
 
+// entries of the jump list
+const MAX_JUMPS: usize = 100;
+
 // ⚠
 
+// "path:line[:column]", both 1-based, as (path, x, y);
+// paths which exist are taken whole, even with colons.
+fn parse_path_arg(arg: &str) -> (&str, Option<(usize, usize)>) {
+    let number = |s: &str| s.parse::<usize>().ok().filter(|n| *n > 0);
+
+    if Path::new(arg).exists() {
+        return (arg, None);
+    }
+
+    let Some((rest, last)) = arg.rsplit_once(':') else {
+        return (arg, None);
+    };
+
+    let Some(last) = number(last) else {
+        return (arg, None);
+    };
+
+    match rest.rsplit_once(':').map(|(path, line)| (path, number(line))) {
+        Some((path, Some(line))) => (path, Some((last - 1, line - 1))),
+        _ => (rest, Some((0, last - 1))),
+    }
+}
+
 fn panic_handler(info: &panic::PanicHookInfo) {
     let bt = backtrace::Backtrace::capture();
     alert!("panic occurred: {info}\n{bt:#?}");
+    tab::remove_clipboard_file();
     restore_term();
 }
 
@@ -41,11 +76,19 @@ pub struct Application {
     fallback_panel_width: u16,
     tree_hover: Option<u16>,
     tab_hover: Option<u16>,
+    toolbar_hover: Option<u16>,
+    last_revealed: Option<FileKey>,
     cursor_list_scroll: u16,
     shown_cursors: u16,
     max_cursor_scroll: u16,
     str_buf: String,
+    deferred_alerts: Vec<String>,
     list: TabList,
+    show_whitespace: bool,
+    // (file, x, y) left by long jumps
+    jump_list: Vec<(Option<FileKey>, usize, usize)>,
+    // entries from here on were jumped back from
+    jump_index: usize,
     stop: bool,
 
     // these three should stay sorted
@@ -68,29 +111,46 @@ impl Application {
         }
     }
 
+    fn update_toolbar(&mut self, actually: bool) {
+        if actually {
+            self.interface.set_toolbar(self.toolbar_hover);
+        }
+    }
+
     fn update_code(&mut self) {
         let tab = self.tabs.current();
-        tab.highlight();
+        let (top, _) = tab.scroll_offsets();
+        let height = self.interface.code_height() as usize;
+        tab.highlight_visible(top, height);
+        tab.layout_rows(self.interface.code_width(), height);
 
         for i in 0..self.interface.code_height() {
             let mut line_no = None;
+            let mut mark = ' ';
+            let mut segment = (0, None);
             self.cursor_buf.clear();
             self.part_buf.clear();
             self.sel_buf.clear();
 
-            let data = if let Some((index, dirty)) = tab.prepare_draw(i) {
+            let data = if let Some((index, dirty, seg)) = tab.prepare_draw(i) {
                 if !dirty {
                     continue;
                 }
 
-                line_no = Some(index + 1);
+                // continued rows have no line number
+                if seg.0 == 0 {
+                    line_no = Some(index + 1);
+                    mark = tab.gutter_mark(index);
+                }
+
+                segment = seg;
                 tab.line_data(index, &mut self.part_buf, &mut self.sel_buf, &mut self.cursor_buf)
             } else {
                 tab::DirtyLine { horizontal_scroll: 0, tab_width_m1: 0, text: "" }
             };
 
             // cursors are sorted
-            let text = ColoredText::new(
+            let mut text = ColoredText::new(
                 data.horizontal_scroll,
                 data.tab_width_m1,
                 &self.cursor_buf,
@@ -99,10 +159,18 @@ impl Application {
                 data.text,
             );
 
-            self.interface.set_code_row(i, line_no, text);
+            text.set_segment(segment);
+            text.set_whitespace(self.show_whitespace);
+            self.interface.set_code_row(i, line_no, mark, text);
         }
     }
 
+    fn update_status(&mut self) {
+        self.str_buf.clear();
+        self.tabs.current().status(&mut self.str_buf);
+        self.interface.set_status(&self.str_buf);
+    }
+
     fn update_left(&mut self, actually: bool) {
         if !actually {
             return;
@@ -113,26 +181,46 @@ impl Application {
 
         let height = self.interface.tree_height();
         let num_cursors = tab.cursor_count() as u16;
+        let max_cursors = max_cursors.min(height.saturating_sub(1));
         let cursor_lines = num_cursors.min(max_cursors);
         let tree_lines = height.saturating_sub(cursor_lines + 1);
         self.forest.check_overscroll();
+        self.forest.check_index(&mut self.tree_select);
+
+        let title = match self.tree_select {
+            Some(_) => " [Folders] ",
+            None => " Folders ",
+        };
+
+        self.interface.write_header(0, title);
 
         if self.shown_cursors != cursor_lines {
             self.shown_cursors = cursor_lines;
-            self.max_cursor_scroll = num_cursors.saturating_sub(max_cursors);
             self.cursor_list_scroll = 0;
         }
 
+        // the cursor count can change without affecting the list height
+        self.max_cursor_scroll = num_cursors - cursor_lines;
+        let scroll = &mut self.cursor_list_scroll;
+        *scroll = (*scroll).min(self.max_cursor_scroll);
+
         for i in 0..tree_lines {
             self.str_buf.clear();
             let maybe_line = self.forest.line(&mut self.str_buf, i);
-            let selected = self.tree_select == maybe_line;
-            let hovered = self.tree_hover == Some(i);
+            let selected = maybe_line.is_some() && self.tree_select == maybe_line;
+            let hovered = maybe_line.is_some() && self.tree_hover == Some(i);
             self.interface.set_tree_row(selected, hovered, i, &self.str_buf);
         }
 
         self.str_buf.clear();
         let _ = write!(self.str_buf, " Cursors: {num_cursors} ");
+
+        if self.max_cursor_scroll > 0 {
+            let above = self.cursor_list_scroll;
+            let below = self.max_cursor_scroll - above;
+            let _ = write!(self.str_buf, "(▲{above} ▼{below}) ");
+        }
+
         self.interface.write_header(tree_lines + 1, &self.str_buf);
 
         for i in 0..cursor_lines {
@@ -153,6 +241,10 @@ impl Application {
                 self.tree_select = None;
                 self.update_left(true);
                 return;
+            } else if tab.cursor_count() > 1 {
+                tab.collapse_to_latest();
+                self.update_left(FOR_CURSORS);
+                return;
             } else if tab.has_selections() {
                 tab.horizontal_jump(0, false);
                 return;
@@ -163,6 +255,89 @@ impl Application {
             true => true,
             false => confirm!("{}", CONFIRM_QUIT),
         };
+
+        if self.stop {
+            self.save_session();
+        }
+    }
+
+    fn save_session(&mut self) {
+        let mut session = Session {
+            panel_width: self.interface.panel_width(),
+            current: 0,
+            tab: Vec::new(),
+        };
+
+        let current = self.tabs.current_index();
+
+        for index in 0..self.tabs.tab_count() {
+            let tab = self.tabs.get_mut(index);
+
+            let Some(key) = tab.key() else {
+                continue;
+            };
+
+            if index == current {
+                session.current = session.tab.len();
+            }
+
+            session.tab.push(TabState {
+                trunk: key.trunk().map(String::from),
+                path: key.path().into(),
+                scroll: tab.scroll_offsets(),
+                cursors: tab.cursor_positions(),
+            });
+        }
+
+        if let Err(error) = session::save(&session) {
+            alert!("Failed to save session: {error}");
+        }
+    }
+
+    // files which no longer exist are skipped
+    fn restore_session(&mut self) {
+        let Some(session) = session::load() else {
+            return;
+        };
+
+        let mut current = None;
+
+        for (i, state) in session.tab.iter().enumerate() {
+            let Ok(text) = fs::read_to_string(&state.path) else {
+                continue;
+            };
+
+            let key = match &state.trunk {
+                Some(trunk) if fs::metadata(trunk).is_ok_and(|m| m.is_dir()) => {
+                    if !self.forest.has_trunk(trunk) {
+                        self.forest.add_local_folder(trunk);
+                    }
+
+                    FileKey::new(trunk.as_str().into(), state.path.clone())
+                },
+                _ => FileKey::fallback(state.path.clone()),
+            };
+
+            self.tabs.open(&self.syntaxes, key, text, false);
+            self.tabs.current().restore_view(&state.cursors, state.scroll);
+
+            if i == session.current {
+                current = Some(self.tabs.current_index());
+            }
+        }
+
+        if let Some(index) = current {
+            self.tabs.switch(index);
+        }
+
+        let width = session.panel_width;
+        self.interface.panel_width_op(&|_| width);
+
+        // the terminal may be narrower than when the session was
+        // saved; a hidden panel stays hidden
+        if width != 0 {
+            self.interface.clamp_panel_width();
+        }
     }
 
     fn scroll(&mut self, delta: isize) {
@@ -184,6 +359,20 @@ impl Application {
         }
     }
 
+    // for paths given on the command line
+    fn open_arg(&mut self, key: FileKey, position: Option<(usize, usize)>) {
+        let Some(FileText { text, lossy }) = self.forest.open(&key) else {
+            return;
+        };
+
+        self.tabs.open(&self.syntaxes, key, text, lossy);
+
+        if let Some((x, y)) = position {
+            self.tabs.current().goto(x, y);
+            self.ensure_cursor_visible();
+        }
+    }
+
     fn ensure_cursor_visible(&mut self) {
         let h = self.interface.code_height() as usize;
         let w = self.interface.code_width();
@@ -191,6 +380,55 @@ impl Application {
         tab.ensure_cursor_visible(w, h);
     }
 
+    fn tree_lines(&self) -> usize {
+        let height = self.interface.tree_height();
+        height.saturating_sub(self.shown_cursors + 1).into()
+    }
+
+    fn focus_tree(&mut self) {
+        if self.forest.is_empty() {
+            alert!("Empty Forest! Please open a folder.");
+            return;
+        }
+
+        let tab = self.tabs.current();
+        let revealed = tab.key().and_then(|key| self.forest.reveal(key));
+        let index = revealed.or(self.tree_select).unwrap_or(0);
+
+        self.tree_select = Some(index);
+        self.ensure_tree_select_visible();
+        self.update_left(true);
+    }
+
+    // follow the focused tab in the forest
+    fn auto_reveal(&mut self) {
+        if !config::auto_reveal() {
+            return;
+        }
+
+        let Some(key) = self.tabs.current().key().cloned() else {
+            return;
+        };
+
+        if self.last_revealed.as_ref() == Some(&key) {
+            return;
+        }
+
+        if let Some(index) = self.forest.reveal(&key) {
+            let height = self.tree_lines();
+            self.forest.ensure_visible(index, height);
+        }
+
+        self.last_revealed = Some(key);
+    }
+
+    fn ensure_tree_select_visible(&mut self) {
+        if let Some(i) = self.tree_select {
+            let height = self.tree_lines();
+            self.forest.ensure_visible(i, height);
+        }
+    }
+
     fn tree_toggle(&mut self, i: usize, index: bool) {
         let bundle = match index {
             true => self.forest.toggle_index(i),
@@ -198,24 +436,95 @@ impl Application {
         };
 
         if let Some(bundle) = bundle {
-            self.tabs.open(&self.syntaxes, bundle.key, bundle.text);
+            let jump = self.jump_position();
+            self.push_jump(jump);
+            let FileText { text, lossy } = bundle.file;
+            self.tabs.open(&self.syntaxes, bundle.key, text, lossy);
+
+            let tab = self.tabs.current();
 
             if let Some(term) = bundle.searched {
-                self.tabs.current().locate(&term);
+                tab.locate(&term);
+            }
+
+            if let Some((x, y)) = bundle.goto {
+                tab.goto(x, y);
                 self.ensure_cursor_visible();
             }
+
+            self.auto_reveal();
         }
 
         self.update_left(true);
         self.update_tab_list(true);
     }
 
+    fn jump_position(&mut self) -> (Option<FileKey>, usize, usize) {
+        let tab = self.tabs.current();
+        let (x, y) = tab.cursor_positions().last().copied().unwrap_or_default();
+        (tab.key().cloned(), x, y)
+    }
+
+    // called before a long jump
+    fn push_jump(&mut self, jump: (Option<FileKey>, usize, usize)) {
+        self.jump_list.truncate(self.jump_index);
+        self.jump_list.push(jump);
+
+        let excess = self.jump_list.len().saturating_sub(MAX_JUMPS);
+        self.jump_list.drain(..excess);
+        self.jump_index = self.jump_list.len();
+    }
+
+    fn jump_back(&mut self) {
+        let Some(index) = self.jump_index.checked_sub(1) else {
+            return;
+        };
+
+        // so that going forward comes back here
+        if self.jump_index == self.jump_list.len() {
+            let here = self.jump_position();
+            self.jump_list.push(here);
+        }
+
+        self.jump_index = index;
+        self.follow_jump();
+    }
+
+    fn jump_forward(&mut self) {
+        if self.jump_index + 1 >= self.jump_list.len() {
+            return;
+        }
+
+        self.jump_index += 1;
+        self.follow_jump();
+    }
+
+    fn follow_jump(&mut self) {
+        let (key, x, y) = self.jump_list[self.jump_index].clone();
+
+        if !self.tabs.switch_to(key.as_ref()) {
+            alert!("This file is no longer open.");
+            return;
+        }
+
+        self.tabs.current().goto(x, y);
+        self.ensure_cursor_visible();
+        self.auto_reveal();
+        self.update_left(FOR_CURSORS);
+        self.update_tab_list(true);
+    }
+
     fn carriage_return(&mut self) {
         if let Some(i) = self.tree_select {
             return self.tree_toggle(i, true);
         }
 
         let tab = self.tabs.current();
+        if tab.read_only() {
+            alert!("{READ_ONLY_ALERT}");
+            return;
+        }
+
         let no_mod = !*tab.modified();
         tab.smart_carriage_return();
         self.ensure_cursor_visible();
@@ -229,6 +538,11 @@ impl Application {
         }
 
         let tab = self.tabs.current();
+        if tab.read_only() {
+            alert!("{READ_ONLY_ALERT}");
+            return;
+        }
+
         let no_mod = !*tab.modified();
         tab.insert_char(c);
         self.ensure_cursor_visible();
@@ -243,6 +557,7 @@ impl Application {
                 false => self.forest.enter_dir(i),
             }
 
+            self.ensure_tree_select_visible();
             self.update_left(true);
         } else {
             let tab = self.tabs.current();
@@ -256,6 +571,7 @@ impl Application {
         if let Some(i) = self.tree_select.as_mut() {
             if !shift {
                 self.forest.up_down(i, delta);
+                self.ensure_tree_select_visible();
                 self.update_left(true);
             }
         } else {
@@ -266,6 +582,13 @@ impl Application {
         }
     }
 
+    fn close_other_tabs(&mut self, index: usize, to_the_right: bool) {
+        self.tabs.close_others(index, to_the_right);
+        self.auto_reveal();
+        self.update_left(FOR_CURSORS);
+        self.update_tab_list(true);
+    }
+
     fn tab_menu(&mut self, col: u16, x: u16, y: u16) {
         use MenuItem::*;
 
@@ -274,32 +597,136 @@ impl Application {
         };
 
         let tab = self.tabs.get_mut(index);
-        let actions = [CloseTab, Syntax, IndentMode];
+        let actions = [
+            CloseTab,
+            CloseOthers,
+            CloseRight,
+            Pin,
+            ReadOnly,
+            Syntax,
+            IndentMode,
+            Retab,
+            LineEndings,
+            Wrap,
+        ];
+
         let close = UserInput::CloseTab(Some(col));
 
         match context_menu(x, y, &actions) {
             Some(CloseTab) => self.handle_event(close),
+            Some(CloseOthers) => self.close_other_tabs(index, false),
+            Some(CloseRight) => self.close_other_tabs(index, true),
+            Some(Pin) => {
+                tab.toggle_pin();
+                self.update_tab_list(true);
+            },
+            Some(ReadOnly) => {
+                tab.toggle_read_only();
+                self.update_tab_list(true);
+            },
             Some(Syntax) => tab.switch_syntax(&self.syntaxes),
             Some(IndentMode) => tab.switch_indent_mode(),
+            Some(Retab | LineEndings) if tab.read_only() => alert!("{READ_ONLY_ALERT}"),
+            Some(Retab) => tab.retab(),
+            Some(LineEndings) => tab.convert_eol(!tab.uses_crlf()),
+            Some(Wrap) => tab.toggle_wrap(),
             _ => (),
         }
     }
 
+    fn incremental_search(&mut self) {
+        let mut needle = String::new();
+        let mut current = None;
+        let mut count = 0;
+        let origin = self.jump_position();
+
+        self.tabs.current().start_search();
+
+        let accept = loop {
+            if self.interface.must_refresh() {
+                self.interface.draw_decorations();
+                self.tabs.current().set_lines_redraw();
+                self.update_tab_list(true);
+                self.update_toolbar(true);
+                self.update_left(true);
+            }
+
+            self.update_code();
+
+            let position = current.map_or(0, |i| i + 1);
+            self.str_buf.clear();
+            let _ = write!(self.str_buf, " Find: {needle}▏ │ {position}/{count} │ ");
+            let _ = write!(self.str_buf, "Enter/↓: Next, ↑: Previous, Tab: Done, Escape: Cancel");
+            self.interface.set_status(&self.str_buf);
+
+            let tab = self.tabs.current();
+
+            match self.interface.search_event(&mut needle) {
+                SearchEvent::Edited => {
+                    count = tab.show_matches(&needle);
+                    current = tab.nearest_match();
+                },
+                SearchEvent::Next => current = current.map(|i| (i + 1) % count),
+                SearchEvent::Previous => {
+                    current = current.map(|i| i.checked_sub(1).unwrap_or(count - 1));
+                },
+                SearchEvent::Accept => break true,
+                SearchEvent::Cancel => break false,
+                SearchEvent::NoOp => continue,
+            }
+
+            match current {
+                Some(i) => tab.select_match(i),
+                None => tab.restore_cursors(),
+            }
+
+            self.ensure_cursor_visible();
+        };
+
+        if accept {
+            self.push_jump(origin);
+        }
+
+        self.tabs.current().end_search(!accept);
+        self.update_left(FOR_CURSORS);
+    }
+
     fn handle_tab_event(&mut self, event: UserInput) {
         if self.tree_select.is_some() {
             return;
         };
 
+        if let UserInput::NextBookmark | UserInput::PrevBookmark = event {
+            let jump = self.jump_position();
+            self.push_jump(jump);
+        }
+
         let tab = self.tabs.current();
         let mut ensure_cursor_visible = false;
         let mut update_tab_list = !*tab.modified();
 
+        if tab.read_only() && event.is_edit() {
+            alert!("{READ_ONLY_ALERT}");
+            return;
+        }
+
         // all of these should update tab list
         match event {
             UserInput::Paste => tab.paste(),
+            UserInput::PasteFromHistory => tab.paste_from_history(),
             UserInput::Cut => tab.cut(),
             UserInput::InsertTab => tab.insert_tab(),
+            UserInput::Dedent => tab.indent_selection(true),
+            UserInput::Replace => tab.find_replace(),
+            UserInput::ToggleComment => tab.toggle_comment(),
+            UserInput::TransformCase(mode) => tab.transform_case(mode),
+            UserInput::Align => tab.align(),
+            UserInput::Surround => tab.ask_surround(),
+            UserInput::Increment => tab.adjust_number(1),
+            UserInput::Decrement => tab.adjust_number(-1),
+            UserInput::MoveLines(up) => tab.move_lines(up),
             UserInput::Backspace(forward) => tab.backspace_once(forward),
+            UserInput::DeleteWord(forward) => tab.delete_word(forward),
             _ => update_tab_list = false,
         }
 
@@ -311,13 +738,34 @@ impl Application {
             _ => ensure_cursor_visible = true,
         };
 
+        if let UserInput::CodeDrag(_, y) | UserInput::BlockDrag(_, y) = event {
+            tab.scroll_at_edge(y, self.interface.code_height());
+        }
+
         match event {
             UserInput::Undo => tab.undo(),
             UserInput::Redo => tab.redo(),
             UserInput::AutoSelect => tab.auto_select(),
+            UserInput::SplitSelection => tab.split_selection_into_lines(),
+            UserInput::SelectInside(around) => tab.select_inside(around, None),
+            UserInput::SelectBlock => tab.select_block(),
+            UserInput::WordJump(f, s) => tab.word_jump(f, s),
+            UserInput::AddCursorVertical(up) => tab.add_cursor_vertical(up),
+            UserInput::CursorsAtLineEdges(end) => tab.cursors_at_line_edges(end),
             UserInput::SeekLineStart(s) => tab.line_seek(true, s),
             UserInput::SeekLineEnd(s) => tab.line_seek(false, s),
-            UserInput::CodeDrag(x, y) => tab.drag_to(x, y),
+            UserInput::CodeDrag(x, y) => tab.extend_to(x, y),
+            UserInput::CodeExtend(x, y) => tab.extend_to(x, y),
+            UserInput::BlockDrag(x, y) => tab.block_drag_to(x, y),
+            UserInput::ToggleWrap => tab.toggle_wrap(),
+            UserInput::ToggleFold => tab.toggle_fold_here(),
+            UserInput::CenterView => tab.center_on_cursor(self.interface.code_height().into()),
+            UserInput::FoldClick(y) => tab.toggle_fold_at_row(y),
+            UserInput::LineSelect(y, extend) => tab.select_line_at_row(y, extend),
+            UserInput::ToggleOvertype => tab.toggle_overtype(),
+            UserInput::ToggleBookmark => tab.toggle_bookmark(),
+            UserInput::NextBookmark => tab.jump_to_bookmark(true),
+            UserInput::PrevBookmark => tab.jump_to_bookmark(false),
             _other => (),
         }
 
@@ -344,6 +792,8 @@ impl Application {
         }
 
         loop {
+            self.interface.draw_panel_width();
+
             let op: &dyn Fn(u16) -> u16 = match self.interface.panel_resize_event() {
                 ResizeEvent::Drag(y) => &move |_| y,
                 ResizeEvent::Right => &|n| n.saturating_add(1),
@@ -353,8 +803,10 @@ impl Application {
             };
 
             self.interface.panel_width_op(op);
+            self.interface.clamp_panel_width();
             self.interface.draw_decorations();
             self.update_tab_list(true);
+            self.update_toolbar(true);
             self.update_left(true);
         }
 
@@ -362,6 +814,169 @@ impl Application {
         self.tabs.current().set_lines_redraw();
     }
 
+    fn reload(&mut self) {
+        let tab = self.tabs.current();
+
+        let Some(key) = tab.key().cloned() else {
+            alert!("Cannot reload: Tab has no underlying file.");
+            return;
+        };
+
+        if *tab.modified() && !confirm!("{}", CONFIRM_RELOAD) {
+            return;
+        }
+
+        if let Some(FileText { text, lossy }) = self.forest.open(&key) {
+            tab.reload(&text);
+
+            if lossy {
+                tab.set_lossy();
+            }
+
+            self.ensure_cursor_visible();
+            self.update_tab_list(true);
+            self.update_left(FOR_CURSORS);
+        }
+    }
+
+    fn open_recent_file(&mut self) {
+        let recent = session::recent_files();
+
+        if recent.is_empty() {
+            alert!("No recently opened files.");
+            return;
+        }
+
+        let mut summary = String::from("Input the number of a file to open:\n");
+
+        for (i, path) in recent.iter().enumerate() {
+            let _ = writeln!(&mut summary, "{}. {path}", i + 1);
+        }
+
+        let Some(input) = prompt!("{summary}") else {
+            return;
+        };
+
+        let index = input.trim().parse::<usize>().ok();
+        let Some(path) = index.and_then(|i| recent.get(i.checked_sub(1)?)) else {
+            alert!("Invalid Number");
+            return;
+        };
+
+        let key = self.forest.key_for_path(path.clone());
+
+        if let Some(FileText { text, lossy }) = self.forest.open(&key) {
+            self.tabs.open(&self.syntaxes, key, text, lossy);
+            self.auto_reveal();
+            self.update_left(true);
+            self.update_tab_list(true);
+        }
+    }
+
+    fn reload_config(&mut self) {
+        if let Some(syntaxes) = config::reload() {
+            self.tabs.reload_syntaxes(&syntaxes);
+            self.syntaxes = syntaxes;
+            self.fallback_panel_width = config::tree_width();
+            interface::set_dirty();
+        }
+    }
+
+    // for tabs without an underlying file
+    fn save_as(&mut self) {
+        let Some(path) = prompt!("Save this tab as (file path):") else {
+            return;
+        };
+
+        let path = path::absolute(path.trim());
+        let Some(path) = path.ok().and_then(|p| p.to_str().map(String::from)) else {
+            alert!("Invalid path");
+            return;
+        };
+
+        if Path::new(&path).exists() && !confirm!("{path} already exists.\nReplace it?") {
+            return;
+        }
+
+        let key = self.forest.key_for_path(path);
+        let tab = self.tabs.current();
+
+        if self.forest.save(&key, tab.save()).is_err() {
+            return;
+        }
+
+        // the new file shows up in the tree
+        let in_tree = key.trunk().is_some();
+        session::push_recent_file(key.path());
+        tab.set_file(&self.syntaxes, key);
+
+        if in_tree {
+            self.forest.refresh();
+            self.update_left(true);
+        }
+
+        self.update_tab_list(true);
+    }
+
+    // saves every modified tab backed by a file
+    fn save_tabs(&mut self, errors: &mut Vec<String>) {
+        for index in 0..self.tabs.tab_count() {
+            let tab = self.tabs.get_mut(index);
+
+            let Some(key) = tab.key().cloned() else {
+                continue;
+            };
+
+            if !*tab.modified() {
+                continue;
+            }
+
+            if let Err(error) = self.save_tab(index, &key) {
+                errors.push(format!("{}: {error}", key.path()));
+            }
+        }
+
+        self.update_tab_list(true);
+    }
+
+    // every save of a file goes through this, so that
+    // replacement chars never overwrite the original bytes
+    fn save_tab(&mut self, index: usize, key: &FileKey) -> Result<(), String> {
+        let tab = self.tabs.get_mut(index);
+
+        if tab.lossy() {
+            return Err(LOSSY_ALERT.into());
+        }
+
+        self.forest.try_save(key, tab.save())?;
+        *tab.modified() = false;
+        Ok(())
+    }
+
+    fn save_all(&mut self) {
+        let mut errors = Vec::new();
+        self.save_tabs(&mut errors);
+
+        for error in errors {
+            alert!("{error}");
+        }
+    }
+
+    fn focus_lost(&mut self) {
+        if config::auto_save() {
+            // we're not in the foreground: report later
+            let mut errors = take(&mut self.deferred_alerts);
+            self.save_tabs(&mut errors);
+            self.deferred_alerts = errors;
+        }
+    }
+
+    fn focus_gained(&mut self) {
+        for error in take(&mut self.deferred_alerts) {
+            alert!("Auto-save failed:\n{error}");
+        }
+    }
+
     fn handle_event(&mut self, event: UserInput) {
         let tab = self.tabs.current();
 
@@ -371,19 +986,27 @@ impl Application {
             UserInput::PanelResize(toggle) => self.resize_left_panel(toggle),
             UserInput::Save => {
                 let Some(key) = tab.key().cloned() else {
-                    alert!("Cannot save: Tab has no underlying file.");
-                    return;
+                    return self.save_as();
                 };
 
-                let text = tab.save();
-                if self.forest.save(&key, text).is_ok() {
-                    *tab.modified() = false;
-                    self.update_tab_list(true);
+                match self.save_tab(self.tabs.current_index(), &key) {
+                    Ok(()) => self.update_tab_list(true),
+                    Err(error) => alert!("{}: {error}", key.path()),
                 }
             },
             UserInput::ContextMenu(Location::TreeRow(row), x, y) => {
                 let is_in_use = |p: &forest::FileKey| self.tabs.is_in_use(p);
-                self.forest.right_click(x, y, row, is_in_use);
+
+                match self.forest.right_click(x, y, row, is_in_use) {
+                    Some(TreeEvent::CopyPath(path)) => self.tabs.current().copy_text(path),
+                    Some(TreeEvent::Renamed(old, new)) => {
+                        self.tabs.rebase(&old, &new);
+                        self.last_revealed = None;
+                        self.update_tab_list(true);
+                    },
+                    None => (),
+                }
+
                 self.update_left(true);
             },
             UserInput::ContextMenu(Location::Tab(col), x, y) => self.tab_menu(col, x, y),
@@ -398,6 +1021,7 @@ impl Application {
                 if let Some(index) = self.interface.find_tab(x, &self.list) {
                     self.tree_select.take();
                     self.tabs.switch(index);
+                    self.auto_reveal();
                     self.update_tab_list(true);
                     self.update_left(FOR_CURSORS);
                 }
@@ -413,18 +1037,21 @@ impl Application {
             },
             UserInput::CloseTab(None) => {
                 self.tabs.close(None);
+                self.auto_reveal();
                 self.update_left(FOR_CURSORS);
                 self.update_tab_list(true);
             },
             UserInput::CloseTab(Some(x)) => {
                 if let Some(index) = self.interface.find_tab(x, &self.list) {
                     self.tabs.close(Some(index));
+                    self.auto_reveal();
                     self.update_left(FOR_CURSORS);
                     self.update_tab_list(true);
                 }
             },
             UserInput::NextTab(leftward) => {
                 self.tabs.next_tab(leftward);
+                self.auto_reveal();
                 self.update_left(FOR_CURSORS);
                 self.update_tab_list(true);
             },
@@ -432,54 +1059,85 @@ impl Application {
                 let update_list = self.tab_hover != Some(x);
                 let cursor_hover = self.cursor_hover.take();
                 let tree_hover = self.tree_hover.take();
+                let toolbar_hover = self.toolbar_hover.take();
                 self.tab_hover = Some(x);
                 self.update_left(tree_hover.is_some() | cursor_hover.is_some());
                 self.update_tab_list(update_list);
+                self.update_toolbar(toolbar_hover.is_some());
             },
             UserInput::TreeHover(y) => {
                 let update_left = self.tree_hover != Some(y);
                 let tab_hover = self.tab_hover.take();
+                let toolbar_hover = self.toolbar_hover.take();
                 self.tree_hover = Some(y);
                 self.update_left(update_left);
                 self.update_tab_list(tab_hover.is_some());
+                self.update_toolbar(toolbar_hover.is_some());
             },
             UserInput::CursorHover(y) => {
                 let update_left = self.cursor_hover != Some(y);
                 let tab_hover = self.tab_hover.take();
+                let toolbar_hover = self.toolbar_hover.take();
                 self.cursor_hover = Some(y);
                 self.update_left(update_left);
                 self.update_tab_list(tab_hover.is_some());
+                self.update_toolbar(toolbar_hover.is_some());
+            },
+            UserInput::ToolbarHover(x) => {
+                let update_toolbar = self.toolbar_hover != Some(x);
+                let cursor_hover = self.cursor_hover.take();
+                let tree_hover = self.tree_hover.take();
+                let tab_hover = self.tab_hover.take();
+                self.toolbar_hover = Some(x);
+                self.update_left(tree_hover.is_some() | cursor_hover.is_some());
+                self.update_tab_list(tab_hover.is_some());
+                self.update_toolbar(update_toolbar);
             },
             UserInput::ClearHover => {
                 let cursor_hover = self.cursor_hover.take();
                 let tree_hover = self.tree_hover.take();
                 let tab_hover = self.tab_hover.take();
+                let toolbar_hover = self.toolbar_hover.take();
                 self.update_left(tree_hover.is_some() | cursor_hover.is_some());
                 self.update_tab_list(tab_hover.is_some());
+                self.update_toolbar(toolbar_hover.is_some());
             },
-            UserInput::Reveal => {
-                let maybe_index = if let Some(key) = tab.key() {
-                    self.forest.reveal(key)
-                } else if self.forest.is_empty() {
-                    None
-                } else {
-                    Some(0)
-                };
-
-                let Some(index) = maybe_index else {
-                    alert!("Empty Forest! Please open a folder.");
-                    return;
-                };
-
-                self.tree_select = Some(index);
-                self.update_left(true);
+            UserInput::Reveal => self.focus_tree(),
+            UserInput::FocusTree => match self.tree_select.take() {
+                Some(_) => self.update_left(true),
+                None => self.focus_tree(),
             },
             UserInput::CarriageReturn => self.carriage_return(),
             UserInput::HorizontalJump(d, s) => self.horizontal_jump(d, s),
             UserInput::VerticalJump(d, s) => self.vertical_jump(d, s),
             UserInput::Resize(w, h) => self.interface.resize(w, h),
+            UserInput::SaveAll => self.save_all(),
+            UserInput::Reload => self.reload(),
+            UserInput::ReloadConfig => self.reload_config(),
+            UserInput::RecentFiles => self.open_recent_file(),
+            UserInput::ToggleWhitespace => {
+                self.show_whitespace = !self.show_whitespace;
+                tab.set_lines_redraw();
+            },
+            UserInput::ToggleHidden => {
+                config::toggle_hidden();
+                self.forest.refresh();
+                self.update_left(true);
+            },
+            UserInput::RefreshTree => {
+                self.forest.refresh();
+                self.update_left(true);
+            },
+            UserInput::FocusLost => self.focus_lost(),
+            UserInput::FocusGained => self.focus_gained(),
             UserInput::Scroll(delta) => self.scroll(delta),
+            UserInput::HorizontalScroll(delta) => tab.h_scroll_by(delta),
             UserInput::Insert(c) => self.insert(c),
+            UserInput::JumpBack => self.jump_back(),
+            UserInput::JumpForward => self.jump_forward(),
+            UserInput::Find if self.tree_select.is_none() && !tab.has_selections() => {
+                self.incremental_search();
+            },
             other => self.handle_tab_event(other),
         }
     }
@@ -496,10 +1154,12 @@ impl Application {
                 self.interface.draw_decorations();
                 tab.set_lines_redraw();
                 self.update_tab_list(true);
+                self.update_toolbar(true);
                 self.update_left(true);
             }
 
             self.update_code();
+            self.update_status();
 
             let event = self
                 .interface
@@ -521,6 +1181,7 @@ fn main() -> Result<(), &'static str> {
     let mut app = Application {
         // state
         str_buf: String::new(),
+        deferred_alerts: Vec::new(),
         cursor_buf: Vec::new(),
         fallback_panel_width,
         list: TabList::new(),
@@ -532,7 +1193,12 @@ fn main() -> Result<(), &'static str> {
         tree_select: None,
         tree_hover: None,
         tab_hover: None,
+        toolbar_hover: None,
+        last_revealed: None,
         shown_cursors: 0,
+        show_whitespace: config::show_whitespace(),
+        jump_list: Vec::new(),
+        jump_index: 0,
         stop: false,
 
         // singletons
@@ -554,6 +1220,8 @@ fn main() -> Result<(), &'static str> {
             return Ok(());
         }
 
+        let (arg, position) = parse_path_arg(&arg);
+
         let Ok(path) = fs::canonicalize(arg) else {
             restore_term();
             return Err("invalid path");
@@ -566,23 +1234,23 @@ fn main() -> Result<(), &'static str> {
 
         match path.is_dir() {
             true => dirs.push(path_str),
-            false => files.push(path_str),
+            false => files.push((path_str, position)),
         }
     }
 
+    if dirs.is_empty() && files.is_empty() {
+        app.restore_session();
+    }
+
     for dir_path in dirs {
         let trunk = app.forest.add_local_folder(&dir_path);
 
         let mut i = 0;
         while i < files.len() {
-            let file_path = &files[i];
+            let (file_path, position) = &files[i];
             if file_path.starts_with(&dir_path) {
                 let key = FileKey::new(trunk.clone(), file_path.clone());
-
-                if let Some(text) = app.forest.open(&key) {
-                    app.tabs.open(&app.syntaxes, key, text);
-                }
-
+                app.open_arg(key, *position);
                 files.remove(i);
                 continue;
             }
@@ -591,18 +1259,15 @@ fn main() -> Result<(), &'static str> {
         }
     }
 
-    for file_path in files {
-        match fs::read_to_string(&file_path) {
-            Ok(text) => {
-                let key = FileKey::fallback(file_path);
-                app.tabs.open(&app.syntaxes, key, text)
-            },
-            Err(err) => alert!("{file_path}: {err}"),
-        }
+    for (file_path, position) in files {
+        let key = FileKey::fallback(file_path);
+        app.open_arg(key, position);
     }
 
+    app.auto_reveal();
     app.run();
     app.interface.close();
+    tab::remove_clipboard_file();
 
     Ok(())
 }
@@ -0,0 +1,131 @@
+use serde::Deserialize;
+use std::fmt::Write;
+use std::{fs, env};
+
+type Pos = (usize, usize);
+
+const MAX_RECENT_FILES: usize = 15;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Session {
+    pub panel_width: u16,
+    pub current: usize,
+    #[serde(default)]
+    pub tab: Vec<TabState>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct TabState {
+    pub trunk: Option<String>,
+    pub path: String,
+    // vertical, horizontal
+    pub scroll: Pos,
+    // latest cursor last
+    pub cursors: Vec<Pos>,
+}
+
+// stored next to the default config file
+fn path(file_name: &str) -> Option<String> {
+    let home = env::var("HOME").ok()?;
+    Some(format!("{home}/.config/hop/{file_name}"))
+}
+
+fn write(file_name: &str, text: String) -> Result<(), String> {
+    let path = path(file_name).ok_or("HOME is not set")?;
+
+    if let Some((dir, _)) = path.rsplit_once('/') {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    fs::write(path, text).map_err(|e| e.to_string())
+}
+
+pub fn load() -> Option<Session> {
+    let text = fs::read_to_string(path("session.toml")?).ok()?;
+    toml::from_str(&text).ok()
+}
+
+// the toml crate is built without its serializer,
+// so this writes the few fields by hand.
+pub fn save(session: &Session) -> Result<(), String> {
+    let mut text = String::new();
+    let _ = writeln!(text, "panel-width = {}", session.panel_width);
+    let _ = writeln!(text, "current = {}", session.current);
+
+    for tab in &session.tab {
+        let _ = writeln!(text, "\n[[tab]]");
+
+        if let Some(trunk) = &tab.trunk {
+            let _ = writeln!(text, "trunk = {}", quote(trunk));
+        }
+
+        let _ = writeln!(text, "path = {}", quote(&tab.path));
+        let _ = writeln!(text, "scroll = [{}, {}]", tab.scroll.0, tab.scroll.1);
+
+        let cursors: Vec<_> = tab
+            .cursors
+            .iter()
+            .map(|(x, y)| format!("[{x}, {y}]"))
+            .collect();
+
+        let _ = writeln!(text, "cursors = [{}]", cursors.join(", "));
+    }
+
+    write("session.toml", text)
+}
+
+// most recent first
+pub fn recent_files() -> Vec<String> {
+    let Some(text) = path("recent.txt").and_then(|p| fs::read_to_string(p).ok()) else {
+        return Vec::new();
+    };
+
+    text.lines().map(String::from).collect()
+}
+
+pub fn push_recent_file(path: &str) {
+    let mut recent = recent_files();
+    recent.retain(|p| p != path);
+    recent.insert(0, path.into());
+    recent.truncate(MAX_RECENT_FILES);
+
+    // not worth bothering the user
+    let _ = write("recent.txt", recent.join("\n"));
+}
+
+// a toml basic string: only quotes,
+// backslashes and control chars are escaped
+fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => _ = write!(quoted, "\\u{:04X}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_paths_are_valid_toml() {
+        for path in ["a/b.rs", "it's", "C:\\x\\\"y\"", "tab\tline\r\nnul\0del\u{7f}", "日本"] {
+            let text = format!("path = {}", quote(path));
+            let table: toml::Table = toml::from_str(&text).unwrap();
+            assert_eq!(table["path"].as_str(), Some(path));
+        }
+    }
+}
@@ -1,10 +1,21 @@
 use serde::Deserialize;
 use litemap::LiteMap;
+use std::borrow::Cow;
 use std::sync::Arc;
 use std::mem::take;
 use crate::alert;
 use RangeMode::*;
 
+// color names of rainbow brackets, by nesting depth
+pub const BRACKET_MODES: [&str; 6] = [
+    "bracket-0",
+    "bracket-1",
+    "bracket-2",
+    "bracket-3",
+    "bracket-4",
+    "bracket-5",
+];
+
 /* CONFIG STRUCT */
 
 #[derive(Copy, Clone, Deserialize, Debug, PartialEq)]
@@ -14,6 +25,8 @@ pub enum NumberType {
     Hex,
     Bin,
     Oct,
+    #[serde(skip)]
+    Float,
 }
 
 #[allow(dead_code)]
@@ -35,11 +48,23 @@ pub struct StringConfig {
 
     #[serde(default)]
     multi_line: bool,
+
+    #[serde(default)]
+    nested: bool,
+
+    #[serde(default)]
+    raw: bool,
+
+    #[serde(default)]
+    fence: Option<char>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct SyntaxConfig {
+    #[serde(skip)]
+    name: String,
+
     extension: String,
     strings_normal: Vec<StringConfig>,
 
@@ -52,6 +77,15 @@ pub struct SyntaxConfig {
     #[serde(default)]
     remap: LiteMap<String, String>,
 
+    #[serde(default)]
+    indent_width: Option<usize>,
+
+    #[serde(default)]
+    hard_tabs: Option<bool>,
+
+    #[serde(default = "default_comment_tags")]
+    comment_tags: Vec<String>,
+
     comment_prefix: Vec<String>,
     keywords_strong: Vec<String>,
     keywords_basic: Vec<String>,
@@ -62,6 +96,10 @@ pub struct SyntaxConfig {
     symbols: Vec<String>,
 }
 
+fn default_comment_tags() -> Vec<String> {
+    ["TODO", "FIXME", "NOTE", "XXX"].map(String::from).to_vec()
+}
+
 #[derive(Deserialize, Debug, Default)]
 pub struct SyntaxFile {
     #[serde(flatten)]
@@ -70,8 +108,16 @@ pub struct SyntaxFile {
 
 impl SyntaxFile {
     pub fn parse(config_str: &str) -> Result<Self, &'static str> {
-        match toml::from_str(config_str) {
-            Ok(config) => Ok(config),
+        match toml::from_str::<Self>(config_str) {
+            Ok(mut config) => {
+                for (name, syntax) in config.inner.iter_mut() {
+                    if let Some(syntax) = Arc::get_mut(syntax) {
+                        syntax.name = name.clone();
+                    }
+                }
+
+                Ok(config)
+            },
             Err(error) => {
                 alert!("failed to parse syntax file: {:?}", error.message());
                 Err("failed to parse syntax file")
@@ -124,6 +170,7 @@ pub enum RangeMode {
     StringNormal,
     StringSpecial,
     Comment,
+    CommentTag,
     StringEscape,
     StringFormat,
     Symbol,
@@ -132,6 +179,8 @@ pub enum RangeMode {
     KeywordBasic,
     KeywordWeak,
     Call(Casing),
+    // index in BRACKET_MODES
+    Bracket(usize),
     #[default]
     Whitespace,
 }
@@ -150,15 +199,18 @@ impl RangeMode {
             StringFormat => "format",
             StringEscape => "escape",
             Comment => "comment",
+            CommentTag => "cmt-tag",
             Symbol => "symbol",
             Whitespace => "wspace",
             Number(NumberType::Hex) => "numhex",
             Number(NumberType::Dec) => "numdec",
             Number(NumberType::Bin) => "numbin",
             Number(NumberType::Oct) => "numoct",
+            Number(NumberType::Float) => "numflt",
             Call(Casing::Mixed) => "cmixed",
             Call(Casing::Lower) => "clower",
             Call(Casing::Upper) => "cupper",
+            Bracket(i) => BRACKET_MODES[i % BRACKET_MODES.len()],
         }
     }
 
@@ -175,15 +227,20 @@ impl RangeMode {
             "format" => StringFormat,
             "escape" => StringEscape,
             "comment" => Comment,
+            "cmt-tag" => CommentTag,
             "symbol" => Symbol,
             "wspace" => Whitespace,
             "numhex" => Number(NumberType::Hex),
             "numdec" => Number(NumberType::Dec),
             "numbin" => Number(NumberType::Bin),
             "numoct" => Number(NumberType::Oct),
+            "numflt" => Number(NumberType::Float),
             "cmixed" => Call(Casing::Mixed),
             "clower" => Call(Casing::Lower),
             "cupper" => Call(Casing::Upper),
+            other if BRACKET_MODES.contains(&other) => {
+                Bracket(BRACKET_MODES.iter().position(|m| *m == other).unwrap())
+            },
             other => {
                 alert!("invalid token type: {other:?}");
                 Comment
@@ -204,17 +261,131 @@ impl Range {
     }
 }
 
+// splits brackets out of symbols, colored by nesting depth;
+// returns the depth at the end of the line.
+pub fn color_brackets(dst: &mut Vec<Range>, line: &str, mut depth: usize, colors: usize) -> usize {
+    let brackets = ['(', ')', '[', ']', '{', '}'];
+
+    if colors == 0 {
+        return 0;
+    }
+
+    let ranges = take(dst);
+    let mut offset = 0;
+
+    for range in ranges {
+        let text = &line[offset..][..range.len];
+        offset += range.len;
+
+        if range.mode != Symbol || !text.contains(brackets) {
+            dst.push(range);
+            continue;
+        }
+
+        let mut symbol_len = 0;
+
+        for c in text.chars() {
+            let color = match c {
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    depth - 1
+                },
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    depth
+                },
+                _ => {
+                    symbol_len += c.len_utf8();
+                    continue;
+                },
+            };
+
+            if symbol_len > 0 {
+                dst.push(Range::new(take(&mut symbol_len), Symbol));
+            }
+
+            dst.push(Range::new(1, Bracket(color % colors)));
+        }
+
+        if symbol_len > 0 {
+            dst.push(Range::new(symbol_len, Symbol));
+        }
+    }
+
+    depth
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum LineContext {
-    Comment(usize),
-    Special(usize),
-    String(usize),
+    // (index, depth of nested comments)
+    Comment(usize, usize),
+    // (index, number of fences)
+    Special(usize, usize),
+    String(usize, usize),
 }
 
 impl SyntaxConfig {
-    fn classify_number(&self, area: &str) -> Option<NumberType> {
-        // todo: floats
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn indent_width(&self) -> Option<usize> {
+        self.indent_width
+    }
+
+    pub fn hard_tabs(&self) -> Option<bool> {
+        self.hard_tabs
+    }
+
+    pub fn comment_prefix(&self) -> Option<&str> {
+        self.comment_prefix.first().map(String::as_str)
+    }
+
+    // digits and glue, except characters with a meaning in floats
+    fn is_digit_or_glue(&self, c: char, radix: u32) -> bool {
+        let is_glue = self.number_glue.contains(&c) && !".eE+-".contains(c);
+        c.is_digit(radix) || is_glue
+    }
+
+    // length of the float literal at the start of `area`, if any
+    fn float_len(&self, area: &str) -> Option<usize> {
+        if !self.numbers.contains(&NumberType::Dec) {
+            return None;
+        }
 
+        if !area.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let run = |from: usize| match area[from..].find(|c| !self.is_digit_or_glue(c, 10)) {
+            Some(i) => from + i,
+            None => area.len(),
+        };
+
+        let starts_with_digit = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+        let mut len = run(0);
+        let mut is_float = false;
+
+        if let Some(fraction) = area[len..].strip_prefix('.') {
+            if starts_with_digit(fraction) {
+                len = run(len + 1);
+                is_float = true;
+            }
+        }
+
+        if let Some(exponent) = area[len..].strip_prefix(['e', 'E']) {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+
+            if starts_with_digit(exponent) {
+                len = run(area.len() - exponent.len());
+                is_float = true;
+            }
+        }
+
+        is_float.then_some(len)
+    }
+
+    fn classify_number(&self, area: &str) -> Option<NumberType> {
         let number_classes = [
             ( "" , 10, NumberType::Dec),
             ("0x", 16, NumberType::Hex),
@@ -225,11 +396,10 @@ impl SyntaxConfig {
         for (prefix, radix, num_type) in number_classes {
             if self.numbers.contains(&num_type) {
                 let Some(number_str) = area.strip_prefix(prefix) else {
-                    break;
+                    continue;
                 };
 
-                let is_glue = |c: char| self.number_glue.contains(&c);
-                let valid_c = |c: char| c.is_digit(radix) || is_glue(c);
+                let valid_c = |c: char| self.is_digit_or_glue(c, radix);
 
                 if number_str.chars().all(valid_c) {
                     return Some(num_type);
@@ -237,7 +407,10 @@ impl SyntaxConfig {
             }
         }
 
-        None
+        match self.float_len(area) == Some(area.len()) {
+            true => Some(NumberType::Float),
+            false => None,
+        }
     }
 
     fn classify_keyword(&self, identifier: &str) -> Option<RangeMode> {
@@ -256,6 +429,66 @@ impl SyntaxConfig {
         None
     }
 
+    // earliest tag in `text` which is a whole word
+    fn find_comment_tag(&self, text: &str) -> Option<(usize, usize)> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+        let find = |tag: &String| text
+            .match_indices(tag.as_str())
+            .find(|(i, _)| {
+                let before = text[..*i].chars().next_back();
+                let after = text[i + tag.len()..].chars().next();
+                !before.is_some_and(is_word) && !after.is_some_and(is_word)
+            })
+            .map(|(i, _)| (i, tag.len()));
+
+        self.comment_tags.iter().filter(|t| !t.is_empty()).filter_map(find).min()
+    }
+
+    fn split_comment_tags(&self, dst: &mut Vec<Range>, line: &str) {
+        if !dst.iter().any(|r| r.mode == Comment) {
+            return;
+        }
+
+        let ranges = take(dst);
+        let mut offset = 0;
+
+        for range in ranges {
+            let mut text = &line[offset..][..range.len];
+            offset += range.len;
+
+            if range.mode != Comment {
+                dst.push(range);
+                continue;
+            }
+
+            while let Some((start, len)) = self.find_comment_tag(text) {
+                if start > 0 {
+                    dst.push(Range::new(start, Comment));
+                }
+
+                dst.push(Range::new(len, CommentTag));
+                text = &text[start + len..];
+            }
+
+            if !text.is_empty() {
+                dst.push(Range::new(text.len(), Comment));
+            }
+        }
+    }
+
+    pub fn highlight(
+        &self,
+        start: Option<LineContext>,
+        dst: &mut Vec<Range>,
+        line: &str,
+    ) -> Option<LineContext> {
+        let ctx = self.tokenize(start, dst, line);
+        self.split_comment_tags(dst, line);
+        self.remap(dst);
+        ctx
+    }
+
     fn remap(&self, ranges: &mut Vec<Range>) {
         for range in ranges.iter_mut() {
             let in_str = range.mode.as_str();
@@ -265,7 +498,7 @@ impl SyntaxConfig {
         }
     }
 
-    pub fn highlight(
+    fn tokenize(
         &self,
         start: Option<LineContext>,
         dst: &mut Vec<Range>,
@@ -275,17 +508,21 @@ impl SyntaxConfig {
         dst.clear();
 
         if let Some(ctx) = start {
-            let (str_cfg, mode) = match ctx {
-                LineContext::Special(i) => (&self.strings_special[i], StringSpecial),
-                LineContext::String(i) => (&self.strings_normal[i], StringNormal),
-                LineContext::Comment(i) => (&self.multi_line_comments[i], Comment),
+            let (str_cfg, mode, mut extra) = match ctx {
+                LineContext::Special(i, e) => (&self.strings_special[i], StringSpecial, e),
+                LineContext::String(i, e) => (&self.strings_normal[i], StringNormal, e),
+                LineContext::Comment(i, e) => (&self.multi_line_comments[i], Comment, e),
             };
 
-            let Some(offset) = str_cfg.find_end(line) else {
+            let Some(offset) = str_cfg.find_ctx_end(line, &mut extra) else {
                 // this line does not change the context
                 dst.push(Range::new(line.len(), mode));
-                self.remap(dst);
-                return start;
+
+                return Some(match ctx {
+                    LineContext::Special(i, _) => LineContext::Special(i, extra),
+                    LineContext::String(i, _) => LineContext::String(i, extra),
+                    LineContext::Comment(i, _) => LineContext::Comment(i, extra),
+                });
             };
 
             dst.push(Range::new(offset, mode));
@@ -309,7 +546,6 @@ impl SyntaxConfig {
                     check_push_ident(&mut ident_len, dst);
                     let mode = Comment;
                     dst.push(Range::new(line.len(), mode));
-                    self.remap(dst);
                     return None;
                 }
             }
@@ -317,7 +553,7 @@ impl SyntaxConfig {
 
             // strings
 
-            type Builder = fn(usize) -> LineContext;
+            type Builder = fn(usize, usize) -> LineContext;
 
             let string_specs = [
                 (&self.strings_special, StringSpecial, LineContext::Special as Builder),
@@ -327,19 +563,19 @@ impl SyntaxConfig {
 
             for (cfg_vec, mode, ctx_gen) in string_specs {
                 for (i, str_cfg) in cfg_vec.iter().enumerate() {
-                    let Some(payload) = line.strip_prefix(&str_cfg.start) else {
+                    let Some((start_len, mut extra)) = str_cfg.match_start(line) else {
                         continue;
                     };
 
                     check_push_ident(&mut ident_len, dst);
+                    let payload = &line[start_len..];
 
-                    let Some(offset) = str_cfg.find_end(payload) else {
+                    let Some(offset) = str_cfg.find_ctx_end(payload, &mut extra) else {
                         dst.push(Range::new(line.len(), mode));
-                        self.remap(dst);
-                        return Some(ctx_gen(i));
+                        return Some(ctx_gen(i, extra));
                     };
 
-                    let len = str_cfg.start.len() + offset;
+                    let len = start_len + offset;
                     dst.push(Range::new(len, mode));
                     line = &payload[offset..];
                     continue 'reparse;
@@ -347,6 +583,17 @@ impl SyntaxConfig {
             }
 
 
+            // floats (before symbols, which may contain dots and signs)
+
+            if ident_len == 0 {
+                if let Some(len) = self.float_len(line) {
+                    dst.push(Range::new(len, Identifier(Casing::Mixed)));
+                    line = &line[len..];
+                    continue 'reparse;
+                }
+            }
+
+
             // symbols
 
             for symbol in &self.symbols {
@@ -425,19 +672,93 @@ impl SyntaxConfig {
             line = &line[range.len..];
         }
 
-        self.remap(dst);
         None
     }
 }
 
 impl StringConfig {
-    fn find_end(&self, mut target: &str) -> Option<usize> {
+    // length of the start token and number of fences in it
+    fn match_start(&self, line: &str) -> Option<(usize, usize)> {
+        let split = self.fence.and_then(|f| Some((f, self.start.split_once(f)?)));
+
+        let Some((fence, (prefix, suffix))) = split else {
+            return line.starts_with(&self.start).then_some((self.start.len(), 0));
+        };
+
+        let fenced = line.strip_prefix(prefix)?;
+        let after = fenced.trim_start_matches(fence);
+        let fences = (fenced.len() - after.len()) / fence.len_utf8();
+        let rest = after.strip_prefix(suffix)?;
+
+        Some((line.len() - rest.len(), fences))
+    }
+
+    // the stop token, with as many fences as the start token had
+    fn stop(&self, fences: usize) -> Cow<'_, str> {
+        let split = self.fence.and_then(|f| Some((f, self.stop.split_once(f)?)));
+
+        match split {
+            Some((fence, (before, after))) => {
+                let fences = String::from(fence).repeat(fences);
+                Cow::Owned(format!("{before}{fences}{after}"))
+            },
+            None => Cow::Borrowed(&self.stop),
+        }
+    }
+
+    // `extra` is the nesting depth for comments, the number of fences for strings
+    fn find_ctx_end(&self, target: &str, extra: &mut usize) -> Option<usize> {
+        match self.nested {
+            true => self.find_nested_end(target, extra),
+            false => self.find_end(target, *extra),
+        }
+    }
+
+    // `depth` counts the inner starts which are still open
+    fn find_nested_end(&self, mut target: &str, depth: &mut usize) -> Option<usize> {
+        let mut skipped = 0;
+
+        loop {
+            let next_stop = target.find(&self.stop);
+            let next_start = target.find(&self.start);
+
+            let Some(stop_index) = next_stop else {
+                *depth += target.matches(&self.start).count();
+
+                return match self.multi_line {
+                    true => None,
+                    false => Some(skipped + target.len()),
+                };
+            };
+
+            if let Some(start_index) = next_start.filter(|i| *i < stop_index) {
+                *depth += 1;
+                let skip = start_index + self.start.len();
+                skipped += skip;
+                target = &target[skip..];
+                continue;
+            }
+
+            let skip = stop_index + self.stop.len();
+            skipped += skip;
+            target = &target[skip..];
+
+            match depth.checked_sub(1) {
+                Some(next) => *depth = next,
+                None => return Some(skipped),
+            }
+        }
+    }
+
+    fn find_end(&self, mut target: &str, fences: usize) -> Option<usize> {
+        let stop = self.stop(fences);
+        let stop = stop.as_ref();
         let mut char_max = 1;
         let mut skipped = 0;
 
         // detect escaped characters
         while let Some((before, after)) = target.split_once('\\') {
-            if before.contains(&self.stop) {
+            if self.raw || before.contains(stop) {
                 break;
             }
 
@@ -456,13 +777,13 @@ impl StringConfig {
             target = &target[skip..];
         }
 
-        if let Some(stop_index) = target.find(&self.stop) {
+        if let Some(stop_index) = target.find(stop) {
             let len = skipped + stop_index;
 
             match self.single_char && len > char_max {
                 // highlight only the start token as fallback
                 true => Some(0),
-                false => Some(len + self.stop.len()),
+                false => Some(len + stop.len()),
             }
         } else {
             let len = skipped + target.len();
@@ -0,0 +1,54 @@
+use super::*;
+
+impl Tab {
+    pub fn toggle_bookmark(&mut self) {
+        let c = self.latest_cursor();
+        let y = self.cursors[c].y;
+
+        match self.bookmarks.binary_search(&y) {
+            Ok(i) => _ = self.bookmarks.remove(i),
+            Err(i) => self.bookmarks.insert(i, y),
+        }
+
+        self.lines[y].must_draw = true;
+    }
+
+    pub fn jump_to_bookmark(&mut self, forward: bool) {
+        let c = self.latest_cursor();
+        let y = self.cursors[c].y;
+
+        let target = match forward {
+            true => self.bookmarks.iter().find(|b| **b > y),
+            false => self.bookmarks.iter().rev().find(|b| **b < y),
+        };
+
+        // wraps around
+        let target = match forward {
+            true => target.or(self.bookmarks.first()),
+            false => target.or(self.bookmarks.last()),
+        };
+
+        let Some(&target) = target else {
+            alert!("No bookmark in this tab.");
+            return;
+        };
+
+        self.goto(0, target);
+    }
+
+    // `delta` lines were inserted (or removed) at `y`
+    pub(super) fn shift_bookmarks(&mut self, y: usize, delta: isize) {
+        for bookmark in self.bookmarks.iter_mut() {
+            if *bookmark >= y {
+                *bookmark = bookmark.saturating_add_signed(delta);
+            }
+        }
+
+        // a removed line merges its bookmark with the previous one
+        self.bookmarks.dedup();
+    }
+
+    pub(super) fn is_bookmarked(&self, y: usize) -> bool {
+        self.bookmarks.binary_search(&y).is_ok()
+    }
+}
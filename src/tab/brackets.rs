@@ -0,0 +1,320 @@
+use std::ops::Range;
+use super::*;
+
+const PAIRS: [(char, char); 5] = [
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+];
+
+type Pos = (usize, usize);
+
+impl Tab {
+    // false if (x, y) is inside a string or a comment
+    fn in_code(&self, x: usize, y: usize) -> bool {
+        let line = &self.lines[y];
+        let offset = line.len_until(x);
+        let mut start = 0;
+
+        for range in line.ranges.iter() {
+            start += range.len;
+
+            if offset < start {
+                return !matches!(
+                    range.mode,
+                    RangeMode::StringNormal
+                        | RangeMode::StringSpecial
+                        | RangeMode::StringEscape
+                        | RangeMode::StringFormat
+                        | RangeMode::Comment
+                        | RangeMode::CommentTag
+                );
+            }
+        }
+
+        true
+    }
+
+    // looks for an unmatched `open` before (x, y), in `lines`
+    fn scan_backward(
+        &self,
+        (open, close): (char, char),
+        x: usize,
+        y: usize,
+        code_only: bool,
+        lines: Range<usize>,
+    ) -> Option<Pos> {
+        let mut depth = 0usize;
+        let mut limit = x;
+
+        for j in (lines.start..=y).rev() {
+            let line = &self.lines[j];
+
+            if j != y {
+                limit = line.len_chars();
+            }
+
+            let end = line.len_until(limit);
+
+            for (k, c) in line.buffer[..end].chars().rev().enumerate() {
+                if code_only && !self.in_code(limit - 1 - k, j) {
+                    continue;
+                }
+
+                if c == close {
+                    depth += 1;
+                } else if c == open {
+                    match depth.checked_sub(1) {
+                        Some(next) => depth = next,
+                        None => return Some((limit - 1 - k, j)),
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // looks for an unmatched `close` from (x, y), in `lines`
+    fn scan_forward(
+        &self,
+        (open, close): (char, char),
+        x: usize,
+        y: usize,
+        code_only: bool,
+        lines: Range<usize>,
+    ) -> Option<Pos> {
+        let mut depth = 0usize;
+        let mut start = x;
+
+        for j in y..lines.end.min(self.lines.len()) {
+            let line = &self.lines[j];
+            let offset = line.len_until(start);
+
+            for (k, c) in line.buffer[offset..].chars().enumerate() {
+                if code_only && !self.in_code(start + k, j) {
+                    continue;
+                }
+
+                if c == open {
+                    depth += 1;
+                } else if c == close {
+                    match depth.checked_sub(1) {
+                        Some(next) => depth = next,
+                        None => return Some((start + k, j)),
+                    }
+                }
+            }
+
+            start = 0;
+        }
+
+        None
+    }
+
+    // quotes don't nest and don't span lines,
+    // so they pair up from the start of the line
+    fn enclosing_quotes(&self, quote: char, x: usize, y: usize) -> Option<(Pos, Pos)> {
+        let mut quotes = Vec::new();
+        let mut escaped = false;
+
+        for (i, c) in self.lines[y].buffer.chars().enumerate() {
+            if c == quote && !escaped {
+                quotes.push(i);
+            }
+
+            escaped = c == '\\' && !escaped;
+        }
+
+        let pair = quotes.chunks_exact(2).find(|pair| pair[0] < x && x <= pair[1])?;
+        Some(((pair[0], y), (pair[1], y)))
+    }
+
+    fn enclosing_pair(&self, pair: (char, char), x: usize, y: usize) -> Option<(Pos, Pos)> {
+        if pair.0 == pair.1 {
+            return self.enclosing_quotes(pair.0, x, y);
+        }
+
+        let lines = 0..self.lines.len();
+        let open = self.scan_backward(pair, x, y, false, lines.clone())?;
+        let close = self.scan_forward(pair, x, y, false, lines)?;
+        Some((open, close))
+    }
+
+    // the bracket right after the cursor, or else right before it
+    fn bracket_next_to(&self, cursor: usize) -> Option<(Pos, (char, char), bool)> {
+        let Cursor { x, y, .. } = self.cursors[cursor];
+        let line = &self.lines[y];
+        let before = x.checked_sub(1);
+        let after = line.buffer.chars().nth(x).map(|c| (x, c));
+        let before = before.and_then(|b| line.buffer.chars().nth(b).map(|c| (b, c)));
+
+        for (bx, c) in after.into_iter().chain(before) {
+            // quotes are symmetric and can't be matched this way
+            for pair in &PAIRS[..3] {
+                let opening = match c {
+                    c if c == pair.0 => true,
+                    c if c == pair.1 => false,
+                    _ => continue,
+                };
+
+                if self.in_code(bx, y) {
+                    return Some(((bx, y), *pair, opening));
+                }
+            }
+        }
+
+        None
+    }
+
+    // unbalanced brackets would make the search go through
+    // the whole file, so it's limited to some `lines`
+    pub fn find_matching_bracket(&self, cursor: usize, lines: Range<usize>) -> Option<Pos> {
+        let ((x, y), pair, opening) = self.bracket_next_to(cursor)?;
+
+        match opening {
+            true => self.scan_forward(pair, x + 1, y, true, lines),
+            false => self.scan_backward(pair, x, y, true, lines),
+        }
+    }
+
+    pub(super) fn update_bracket_match(&mut self, lines: Range<usize>) {
+        let c = self.latest_cursor();
+
+        let bracket_match = match self.find_matching_bracket(c, lines) {
+            Some(partner) => self.bracket_next_to(c).map(|(pos, ..)| [pos, partner]),
+            None => None,
+        };
+
+        if bracket_match == self.bracket_match {
+            return;
+        }
+
+        let old = replace(&mut self.bracket_match, bracket_match);
+
+        for (_x, y) in old.into_iter().chain(bracket_match).flatten() {
+            if let Some(line) = self.lines.get_mut(y) {
+                line.must_draw = true;
+            }
+        }
+    }
+
+    pub fn select_inside(&mut self, around: bool, pair: Option<(char, char)>) {
+        let pairs: &[(char, char)] = match pair.as_ref() {
+            Some(pair) => std::slice::from_ref(pair),
+            None => &PAIRS,
+        };
+
+        for c in 0..self.cursors.len() {
+            let Cursor { x, y, .. } = self.cursors[c];
+
+            // the nearest opening delimiter wins
+            let candidates = pairs.iter().filter_map(|p| self.enclosing_pair(*p, x, y));
+            let nearest = candidates.max_by_key(|((ox, oy), _)| (*oy, *ox));
+
+            let Some(((ox, oy), (cx, cy))) = nearest else {
+                continue;
+            };
+
+            let (start_x, end_x) = match around {
+                true => (ox, cx + 1),
+                false => (ox + 1, cx),
+            };
+
+            let cursor = &mut self.cursors[c];
+            cursor.x = end_x;
+            cursor.y = cy;
+            cursor.sel_x = start_x as isize - end_x as isize;
+            cursor.sel_y = oy as isize - cy as isize;
+        }
+
+        self.check_cursors();
+        self.set_lines_redraw();
+    }
+
+    pub fn ask_surround(&mut self) {
+        let l1 = "Surround selections with which delimiters?";
+        let l2 = "Brackets and quotes are closed automatically,";
+        let l3 = "other closing delimiters follow a space: '<b> </b>'.";
+
+        let Some(input) = prompt!("{l1}\n{l2}\n{l3}\n\n") else {
+            return;
+        };
+
+        // surrounding with nothing would only be logged
+        if input.is_empty() {
+            return;
+        }
+
+        let mut chars = input.chars();
+        let closing = |c| PAIRS.iter().chain(&[('<', '>')]).find(|(o, _)| *o == c);
+
+        let (open, close) = match (chars.next(), chars.next()) {
+            (Some(c), None) => match closing(c) {
+                Some((_, close)) => (input.clone(), String::from(*close)),
+                None => (input.clone(), input.clone()),
+            },
+            _ => match input.split_once(' ') {
+                Some((open, close)) => (open.into(), close.into()),
+                None => (input.clone(), input.clone()),
+            },
+        };
+
+        self.surround(&open, &close);
+    }
+
+    // selections are kept between the delimiters
+    pub fn surround(&mut self, open: &str, close: &str) {
+        let open_len = open.chars().count();
+        self.prepare_insertion();
+
+        for c in 0..self.cursors.len() {
+            let backup = self.cursors[c];
+            let (mut start, mut end) = (backup, backup);
+
+            start.sel_jump(true);
+            end.sel_jump(false);
+
+            // closing first, so that the start stays in place
+            self.cursors[c] = Cursor { sel_x: 0, sel_y: 0, ..end };
+            self.insert_text_cursor(c, close);
+
+            self.cursors[c] = Cursor { sel_x: 0, sel_y: 0, ..start };
+            self.insert_text_cursor(c, open);
+
+            let shift = |y: usize| match y == start.y {
+                true => open_len,
+                false => 0,
+            };
+
+            let origin_y = backup.y.saturating_add_signed(backup.sel_y);
+            let origin_x = backup.x.saturating_add_signed(backup.sel_x) + shift(origin_y);
+
+            let cursor = &mut self.cursors[c];
+            *cursor = backup;
+            cursor.x += shift(backup.y);
+            cursor.sel_x = origin_x as isize - cursor.x as isize;
+        }
+
+        self.modified = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_pair_up_from_line_start() {
+        let tab = Tab::new(None, None, r#"f("a" x "b\"c")"#.into());
+
+        // between two strings
+        assert_eq!(tab.enclosing_quotes('"', 6, 0), None);
+        assert_eq!(tab.enclosing_quotes('"', 3, 0), Some(((2, 0), (4, 0))));
+
+        // escaped quotes don't count
+        assert_eq!(tab.enclosing_quotes('"', 10, 0), Some(((8, 0), (13, 0))));
+    }
+}
@@ -1,70 +1,298 @@
-use std::process::Command;
-use crate::config::internal_clipboard;
+use std::process::{self, Command};
+use std::io::{self, stdout, Write as _};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, path::PathBuf};
+use crate::config::{internal_clipboard, copy_whole_line, clipboard_backend, ClipboardBackend};
 use super::*;
 
-const TMP_PATH: &str = "/tmp/hop-clipboard.txt";
-const DELIMITER: &str = " \n";
+// external apps don't know about our regions, so
+// their copy of the text has them on separate lines;
+// pasting never splits text on it, regions are kept
+const DELIMITER: &str = "\n";
+const HISTORY_LEN: usize = 10;
+const PREVIEW_LEN: usize = 40;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// one directory per process, so that instances and users don't share it
+static TMP_DIR: OnceLock<Result<String, String>> = OnceLock::new();
+
+// created on first use, only the current user can enter it;
+// an existing path is never reused
+fn tmp_dir() -> io::Result<&'static str> {
+    let dir = TMP_DIR.get_or_init(|| {
+        let base = env::var("XDG_RUNTIME_DIR").map(PathBuf::from);
+        let base = base.unwrap_or_else(|_| env::temp_dir());
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+        let dir = format!("{}/hop-{}-{nanos}", base.display(), process::id());
+
+        match fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => Ok(dir),
+            Err(error) => Err(format!("{dir}: {error}")),
+        }
+    });
+
+    dir.as_deref().map_err(|error| io::Error::other(error.clone()))
+}
+
+fn tmp_path() -> io::Result<String> {
+    Ok(format!("{}/clipboard.txt", tmp_dir()?))
+}
+
+// nobody else can write in our directory, so
+// an existing file is the one we left there
+fn create_tmp_file() -> io::Result<fs::File> {
+    let path = tmp_path()?;
+
+    if let Err(error) = fs::remove_file(&path) {
+        if error.kind() != io::ErrorKind::NotFound {
+            return Err(error);
+        }
+    }
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+// called when quitting
+pub fn remove_clipboard_file() {
+    if let Some(Ok(dir)) = TMP_DIR.get() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+// one text per cursor
+type Regions = Vec<String>;
+
+// most recent first, shared by all tabs;
+// regions are kept apart, whatever their text
+static HISTORY: Mutex<Vec<Regions>> = Mutex::new(Vec::new());
+
+// the last copy to external apps, whose regions
+// are found again when it's pasted back
+static EXTERNAL_COPY: Mutex<Regions> = Mutex::new(Vec::new());
+
+// OSC 52 can't be read back, so pasting uses the last copy
+fn paste_internally() -> bool {
+    internal_clipboard() || clipboard_backend() == ClipboardBackend::Osc52
+}
+
+fn push_history(regions: Regions) {
+    let mut history = HISTORY.lock().unwrap();
+    history.retain(|r| *r != regions);
+    history.insert(0, regions);
+    history.truncate(HISTORY_LEN);
+}
+
+fn last_copy() -> Regions {
+    let history = HISTORY.lock().unwrap();
+    history.first().cloned().unwrap_or_default()
+}
+
+fn store_clipboard(regions: Regions) {
+    if internal_clipboard() {
+        push_history(regions);
+        return;
+    }
+
+    if clipboard_backend() == ClipboardBackend::Osc52 {
+        osc52_copy(&regions.join(DELIMITER));
+        push_history(regions);
+        return;
+    }
+
+    let text = regions.join(DELIMITER);
+
+    if let Err(error) = create_tmp_file().and_then(|mut f| f.write_all(text.as_bytes())) {
+        alert!("failed to write clipboard-file:\n{error:?}");
+        return;
+    }
+
+    *EXTERNAL_COPY.lock().unwrap() = regions;
+    try_exec(true);
+}
+
+// single line, shortened
+fn preview(text: &str) -> String {
+    let mut preview: String = text
+        .chars()
+        .map(|c| if c == '\n' { '⏎' } else { c })
+        .take(PREVIEW_LEN)
+        .collect();
+
+    if text.chars().nth(PREVIEW_LEN).is_some() {
+        preview.push('…');
+    }
+
+    preview
+}
 
 impl Tab {
+    // with no selection at all, select the line of each cursor;
+    // cutting the last line also takes the line feed above it
+    fn select_lines(&mut self, cut: bool) -> bool {
+        if self.has_selections() || !copy_whole_line() {
+            return false;
+        }
+
+        let last_y = self.lines.len() - 1;
+        let above = self.cursors.iter().any(|c| c.y + 1 == last_y);
+        let prev_len = last_y.checked_sub(1).map(|y| self.lines[y].len_chars());
+
+        for cursor in self.cursors.iter_mut() {
+            if cursor.y < last_y {
+                cursor.x = 0;
+                cursor.y += 1;
+                cursor.sel_x = 0;
+                cursor.sel_y = -1;
+            } else {
+                cursor.x = self.lines[cursor.y].len_chars();
+                cursor.sel_x = -(cursor.x as isize);
+                cursor.sel_y = 0;
+
+                if let (true, false, Some(prev_len)) = (cut, above, prev_len) {
+                    cursor.sel_x += prev_len as isize;
+                    cursor.sel_y = -1;
+                }
+            }
+        }
+
+        // cursors on the same line select it once
+        self.check_cursors();
+        self.cursors.dedup_by(|a, b| (a.x, a.y, a.sel_x, a.sel_y) == (b.x, b.y, b.sel_x, b.sel_y));
+        true
+    }
+
     pub fn copy(&mut self) {
-        let cursors = self.cursors.len();
-        let mut text = String::new();
+        let backup = self.cursors.clone();
 
-        for c in 0..cursors {
+        if self.select_lines(false) {
+            self.copy_selections(true);
+            self.cursors = backup;
+        } else {
+            self.copy_selections(false);
+        }
+    }
+
+    fn copy_selections(&mut self, line_wise: bool) {
+        let mut regions = Vec::with_capacity(self.cursors.len());
+
+        for c in 0..self.cursors.len() {
+            let mut text = String::new();
             self.extract_selection(c, &mut text);
 
-            if c + 1 < cursors {
-                text += DELIMITER;
+            // the last line has no line feed, but
+            // cutting it took the one above it
+            if line_wise && !text.ends_with('\n') {
+                if text.starts_with('\n') {
+                    text.remove(0);
+                }
+
+                text.push('\n');
             }
-        }
 
-        if internal_clipboard() {
-            self.internal_clipboard = text;
-            return;
+            regions.push(text);
         }
 
-        if let Err(error) = fs::write(TMP_PATH, text) {
-            alert!("failed to write clipboard-file ({TMP_PATH}):\n{error:?}");
-            return;
+        self.line_copy.clear();
+        if line_wise {
+            self.line_copy += &regions.join(DELIMITER);
         }
 
-        try_exec(true);
+        store_clipboard(regions);
     }
 
+    pub fn copy_text(&mut self, text: String) {
+        self.line_copy.clear();
+        store_clipboard(vec![text]);
+    }
+
+    // leaves collapsed cursors at the start of what was selected;
+    // line-wise, that's the start of the line below the cut ones
     pub fn cut(&mut self) {
-        self.copy();
+        let line_wise = self.select_lines(true);
+        self.copy_selections(line_wise);
         self.erase_selection();
     }
 
+    pub fn paste_from_history(&mut self) {
+        let history = HISTORY.lock().unwrap().clone();
+
+        if history.is_empty() {
+            alert!("The clipboard history is empty.");
+            return;
+        }
+
+        let mut summary = String::from("Input the number of an entry to paste:\n");
+
+        for (i, regions) in history.iter().enumerate() {
+            let _ = writeln!(&mut summary, "{}. {}", i + 1, preview(&regions.join(DELIMITER)));
+        }
+
+        let Some(input) = prompt!("{summary}") else {
+            return;
+        };
+
+        let index = input.trim().parse::<usize>().ok();
+        let Some(regions) = index.and_then(|i| history.get(i.checked_sub(1)?)) else {
+            alert!("Invalid Number");
+            return;
+        };
+
+        self.paste_regions(regions.clone());
+    }
+
     pub fn paste(&mut self) {
-        let text = if internal_clipboard() {
-            self.internal_clipboard.clone()
+        let regions = if paste_internally() {
+            last_copy()
         } else {
             try_exec(false);
 
-            let Ok(contents) = fs::read_to_string(TMP_PATH) else {
-                alert!("failed to read clipboard-file ({TMP_PATH})");
-                return;
+            let contents = match tmp_path().and_then(fs::read_to_string) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    alert!("failed to read clipboard-file:\n{error:?}");
+                    return;
+                },
             };
 
-            contents
+            // other apps' text is a single region
+            let external = EXTERNAL_COPY.lock().unwrap();
+            match external.join(DELIMITER) == contents {
+                true => external.clone(),
+                false => vec![contents],
+            }
         };
 
-        let cursors = self.cursors.len();
+        self.paste_regions(regions);
+    }
+
+    fn paste_regions(&mut self, regions: Regions) {
+        let text = regions.join(DELIMITER);
 
-        if cursors > 1 {
-            let regions = text.split(DELIMITER).count();
+        // line-wise paste: insert above the current line
+        let line_wise = !self.line_copy.is_empty() && self.line_copy == text;
 
-            if regions != cursors {
-                alert!("cannot paste: {regions} clipboard regions but {cursors} cursors");
-                return;
+        if line_wise && !self.has_selections() {
+            for cursor in self.cursors.iter_mut() {
+                self.lines[cursor.y].must_draw = true;
+                cursor.x = 0;
             }
+        }
+
+        let cursors = self.cursors.len();
+        let count = regions.len();
 
+        // otherwise, the whole text goes to every cursor
+        if cursors > 1 && count == cursors {
             self.prepare_insertion();
             self.erase_selection();
 
-            let iter = text.split(DELIMITER);
-            for (c, region) in iter.enumerate() {
+            for (c, region) in regions.iter().enumerate() {
                 self.insert_text_cursor(c, region);
             }
 
@@ -79,13 +307,16 @@ fn try_exec(copy: bool) {
     let mut success = false;
 
     let maybe_file = match copy {
-        true => fs::File::open(TMP_PATH),
-        false => fs::File::create(TMP_PATH),
+        true => tmp_path().and_then(fs::File::open),
+        false => create_tmp_file(),
     };
 
-    let Ok(file) = maybe_file else {
-        alert!("failed to open clipboard-file ({TMP_PATH})");
-        return;
+    let file = match maybe_file {
+        Ok(file) => file,
+        Err(error) => {
+            alert!("failed to open clipboard-file:\n{error:?}");
+            return;
+        },
     };
 
     let candidates: [(&str, &[&str]); 3] = match copy {
@@ -123,3 +354,27 @@ fn try_exec(copy: bool) {
         alert!("{ln1}\n{ln2}\n{ln3}");
     }
 }
+
+fn osc52_copy(text: &str) {
+    let mut stdout = stdout();
+    let _ = write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    let _ = stdout.flush();
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(BASE64[(n >> (18 - 6 * i)) & 63] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
@@ -20,19 +20,22 @@ impl Tab {
     }
 
     fn merge_with_prev_line(&mut self, c: usize, this_y: usize, prev_y: usize) {
-        let mut line = self.lines.remove(this_y);
+        let mut line = self.remove_line(this_y);
+        self.shift_lines(this_y, -1);
         let buf = take(&mut line.buffer);
 
-        let prev = &mut self.lines[prev_y];
+        let prev = self.line_mut(prev_y);
         let x_add = prev.len_chars();
         prev.buffer += buf.as_str();
+        prev.set_dirty();
 
-        self.set_lines_dirty(prev_y);
+        self.set_lines_redraw_from(prev_y + 1);
         self.backspace_cursor(c, true, x_add as isize);
     }
 
-    fn backspace(&mut self, c: usize, mut num_chars: usize) {
-        let simple_backspace = num_chars == 1;
+    // with `soft_tabs`, a single char before a soft tab erases the tab
+    fn backspace(&mut self, c: usize, mut num_chars: usize, soft_tabs: bool) {
+        let simple_backspace = soft_tabs && num_chars == 1;
 
         while self.cursors[c].x < num_chars {
             let this_y = self.cursors[c].y;
@@ -50,7 +53,7 @@ impl Tab {
 
         // mutable copy! not mut ref
         let cursor = self.cursors[c];
-        let line = &mut self.lines[cursor.y];
+        let line = &self.lines[cursor.y];
 
         let old_i = line.len_until(cursor.x);
 
@@ -62,12 +65,12 @@ impl Tab {
             num_chars = self.tab_string.chars().count();
         }
 
+        let line = self.line_mut(cursor.y);
         let new_i = line.len_until(cursor.x - num_chars);
 
         line.buffer.replace_range(new_i..old_i, "");
         line.set_dirty();
 
-        self.check_line_highlighting(cursor.y);
         self.backspace_cursor(c, false, -(num_chars as isize));
     }
 
@@ -80,7 +83,37 @@ impl Tab {
             }
 
             for c in 0..self.cursors.len() {
-                self.backspace(c, 1);
+                self.backspace(c, 1, true);
+            }
+        }
+
+        self.modified = true;
+    }
+
+    pub fn delete_word(&mut self, forward: bool) {
+        if !self.erase_selection() {
+            self.prepare_deletion();
+            let mut lengths = Vec::with_capacity(self.cursors.len());
+
+            for c in 0..self.cursors.len() {
+                let delta = self.word_delta(c, forward);
+                let backup = self.cursors[c];
+
+                if forward {
+                    self.hor_jump_cursor(c, delta, false);
+                }
+
+                // nothing to delete at the end of the file
+                let len = match self.cursors[c] == backup && forward {
+                    true => 0,
+                    false => delta.unsigned_abs(),
+                };
+
+                lengths.push(len);
+            }
+
+            for (c, len) in lengths.into_iter().enumerate() {
+                self.backspace(c, len, true);
             }
         }
 
@@ -93,6 +126,13 @@ impl Tab {
         }
 
         self.prepare_deletion();
+        self.erase_selection_unlogged();
+        true
+    }
+
+    // each cursor ends up collapsed where its selection
+    // started, whichever side the selection was made from
+    pub(super) fn erase_selection_unlogged(&mut self) {
         let range = 0..self.cursors.len();
         for c in range.rev() {
             let cursor = &mut self.cursors[c];
@@ -110,7 +150,7 @@ impl Tab {
                 let x = cursor.x;
                 cursor.sel_y += 1;
                 cursor.sel_x += x as isize;
-                self.backspace(c, x + 1);
+                self.backspace(c, x + 1, false);
 
                 let cursor = &mut self.cursors[c];
                 cursor.sel_x -= cursor.x as isize;
@@ -118,12 +158,10 @@ impl Tab {
 
             let cursor = &mut self.cursors[c];
             let sel_x = take(&mut cursor.sel_x);
-            self.backspace(c, -sel_x as usize);
+            self.backspace(c, -sel_x as usize, false);
         }
 
         // todo: do better
         self.set_fully_dirty();
-
-        true
     }
 }
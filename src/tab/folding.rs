@@ -0,0 +1,211 @@
+use super::*;
+
+// cells of leading whitespace, none for blank lines
+fn indent_cells(line: &Line, tab_width_m1: usize) -> Option<usize> {
+    let trimmed = line.buffer.trim_start_matches([' ', '\t']);
+
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let indent = &line.buffer[..line.buffer.len() - trimmed.len()];
+    Some(indent.chars().map(|c| char_width(c, tab_width_m1)).sum())
+}
+
+// last line of the block below `header`, which is more indented
+fn fold_end(lines: &[Line], header: usize, tab_width_m1: usize) -> Option<usize> {
+    let base = indent_cells(&lines[header], tab_width_m1)?;
+    let mut end = None;
+
+    for (y, line) in lines.iter().enumerate().skip(header + 1) {
+        match indent_cells(line, tab_width_m1) {
+            // blank lines only count inside the block
+            None => continue,
+            Some(cells) if cells > base => end = Some(y),
+            Some(_) => break,
+        }
+    }
+
+    end
+}
+
+impl Tab {
+    // false for fold headers
+    pub(super) fn is_folded(&self, y: usize) -> bool {
+        self.folds.iter().any(|(header, last)| (header + 1..=*last).contains(&y))
+    }
+
+    // the nearest shown line, looking in one direction
+    pub(super) fn skip_folds(&self, mut y: usize, down: bool) -> usize {
+        while self.is_folded(y) {
+            let header = self.folds.iter().filter(|(h, l)| (h + 1..=*l).contains(&y));
+
+            y = match down {
+                true => header.map(|(_, l)| *l).max().unwrap() + 1,
+                false => header.map(|(h, _)| *h).min().unwrap(),
+            };
+
+            // nothing is shown below
+            if y >= self.lines.len() {
+                return self.skip_folds(self.lines.len() - 1, false);
+            }
+        }
+
+        y
+    }
+
+    pub fn toggle_fold(&mut self, y: usize) {
+        if let Some(i) = self.folds.iter().position(|(header, _)| *header == y) {
+            self.folds.remove(i);
+        } else if let Some(last) = fold_end(&self.lines, y, self.tab_width_m1) {
+            let i = self.folds.partition_point(|(header, _)| *header < y);
+            self.folds.insert(i, (y, last));
+            self.leave_folds();
+        }
+
+        self.rows.clear();
+        self.set_lines_redraw();
+    }
+
+    pub fn toggle_fold_here(&mut self) {
+        let c = self.latest_cursor();
+        self.toggle_fold(self.cursors[c].y);
+    }
+
+    pub fn toggle_fold_at_row(&mut self, screen_y: u16) {
+        if let Some((y, 0)) = self.screen_line(screen_y) {
+            self.toggle_fold(y);
+        }
+    }
+
+    // cursors in a fold move to its header
+    fn leave_folds(&mut self) {
+        for c in 0..self.cursors.len() {
+            let Cursor { y, .. } = self.cursors[c];
+            let header = self.skip_folds(y, false);
+
+            if header != y {
+                let x = self.lines[header].len_chars();
+                let cursor = &mut self.cursors[c];
+                (cursor.x, cursor.y) = (x, header);
+                (cursor.sel_x, cursor.sel_y) = (0, 0);
+            }
+        }
+
+        self.check_cursors();
+    }
+
+    // the latest cursor was moved into a fold
+    pub(super) fn unfold_cursor(&mut self) {
+        let c = self.latest_cursor();
+        let y = self.cursors[c].y;
+        let len = self.folds.len();
+
+        self.folds.retain(|(header, last)| !(header + 1..=*last).contains(&y));
+
+        if self.folds.len() != len {
+            self.rows.clear();
+            self.set_lines_redraw();
+        }
+    }
+
+    // `delta` lines were inserted (or removed) at `y`
+    pub(super) fn shift_folds(&mut self, y: usize, delta: isize) {
+        for (header, last) in self.folds.iter_mut() {
+            if *header >= y {
+                *header = header.saturating_add_signed(delta);
+            }
+
+            if *last >= y {
+                *last = last.saturating_add_signed(delta);
+            }
+        }
+
+        self.folds_dirty = true;
+    }
+
+    // folds follow their block, or disappear with it
+    pub(super) fn check_folds(&mut self) {
+        if !take(&mut self.folds_dirty) {
+            return;
+        }
+
+        let lines = &self.lines;
+        let tab_width_m1 = self.tab_width_m1;
+
+        self.folds.retain_mut(|(header, last)| {
+            let end = (*header < lines.len()).then(|| fold_end(lines, *header, tab_width_m1));
+
+            match end.flatten() {
+                Some(end) => *last = end,
+                None => return false,
+            }
+
+            true
+        });
+
+        self.folds.dedup_by_key(|(header, _)| *header);
+    }
+
+    // selects the smallest indented block around the latest
+    // cursor's selection, which grows with each call
+    pub fn select_block(&mut self) {
+        let c = self.latest_cursor();
+        let (mut start, mut end) = (self.cursors[c], self.cursors[c]);
+
+        start.sel_jump(true);
+        end.sel_jump(false);
+
+        let num_lines = self.lines.len();
+        let indent = |y: usize| indent_cells(&self.lines[y], self.tab_width_m1);
+        let mut level = (start.y..num_lines).find_map(indent).unwrap_or(0);
+
+        let (first, last) = loop {
+            let inside = |y: &usize| indent(*y).is_none_or(|cells| cells >= level);
+            let top = (0..=start.y).rev().take_while(inside).last().unwrap_or(start.y);
+            let bottom = (end.y..num_lines).take_while(inside).last().unwrap_or(end.y);
+
+            // blank lines around the block are left out
+            let mut first = top;
+            let mut last = bottom;
+
+            while first < last && indent(first).is_none() {
+                first += 1;
+            }
+
+            while last > first && indent(last).is_none() {
+                last -= 1;
+            }
+
+            let last_len = self.lines[last].len_chars();
+            let contains = first <= start.y && last >= end.y;
+            let grows = (first, 0, last, last_len) != (start.y, start.x, end.y, end.x);
+
+            if contains && grows {
+                break (first, last);
+            }
+
+            // the block of the closest less indented line
+            let above = top.checked_sub(1);
+            let below = Some(bottom + 1).filter(|y| *y < num_lines);
+
+            match [above, below].into_iter().flatten().filter_map(indent).max() {
+                Some(cells) => level = cells,
+                None => return,
+            }
+        };
+
+        let x = self.lines[last].len_chars();
+        let cursor = &mut self.cursors[c];
+        (cursor.x, cursor.y) = (x, last);
+        cursor.sel_x = -(x as isize);
+        cursor.sel_y = first as isize - last as isize;
+
+        self.check_cursors();
+        self.set_lines_redraw();
+    }
+
+    pub(super) fn is_fold_header(&self, y: usize) -> bool {
+        self.folds.iter().any(|(header, _)| *header == y)
+    }
+}
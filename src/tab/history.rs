@@ -1,3 +1,5 @@
+use std::time::Instant;
+use crate::config::{undo_depth, undo_merge_window};
 use super::*;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -6,19 +8,46 @@ enum Edition {
     Deletion,
 }
 
-#[derive(Default)]
-struct RawSnapshot {
-    cursors: Vec<Cursor>,
+// what a line held before an edit
+#[derive(Debug, PartialEq, Eq)]
+struct LineText {
     buffer: String,
+    eol_cr: bool,
+}
+
+impl LineText {
+    fn of(line: &Line) -> Self {
+        Self {
+            buffer: line.buffer.clone(),
+            eol_cr: line.eol_cr,
+        }
+    }
 }
 
+// applying an edit turns it into its inverse: undoing
+// applies a group backwards, redoing applies it forwards
+#[derive(Debug, PartialEq, Eq)]
+enum LineEdit {
+    Changed(usize, LineText),
+    Inserted(usize),
+    Removed(usize, LineText),
+}
+
+// state before an edit group, with the
+// line edits leading to the next one
 struct Snapshot {
-    raw: RawSnapshot,
+    cursors: Vec<Cursor>,
+    // vertical, horizontal
+    scroll: (usize, usize),
+    edits: Vec<LineEdit>,
     before: Edition,
+    // latest edit merged into this snapshot
+    time: Instant,
 }
 
 pub struct History {
-    pre_undo: Option<RawSnapshot>,
+    // cursors and scroll before the first undo
+    pre_undo: Option<(Vec<Cursor>, (usize, usize))>,
     inner: Vec<Snapshot>,
     len: Option<usize>,
 }
@@ -36,25 +65,40 @@ impl History {
         assert!(self.len.is_none());
         self.len = Some(0);
     }
-}
 
-impl Tab {
-    fn raw_snapshot(&mut self) -> RawSnapshot {
-        self.rebuild();
+    pub fn is_active(&self) -> bool {
+        self.len.is_some()
+    }
 
-        RawSnapshot {
-            cursors: self.cursors.clone(),
-            buffer: self.tmp_buf.clone(),
+    // edits of the current group, if any
+    fn edits(&mut self) -> Option<&mut Vec<LineEdit>> {
+        let len = self.len?;
+
+        // undone groups can't be redone after an edit
+        if len < self.inner.len() {
+            self.inner.truncate(len);
+            self.pre_undo.take();
         }
+
+        self.inner.last_mut().map(|snapshot| &mut snapshot.edits)
     }
+}
 
+impl Tab {
     fn log(&mut self, before: Edition) {
         let Some(len) = self.history.len.as_mut() else {
             return;
         };
 
+        let now = Instant::now();
+
+        // rapid edits of the same kind are undone together
         if let Some(i) = len.checked_sub(1) {
-            if self.history.inner[i].before == before {
+            let last = &mut self.history.inner[i];
+            let rapid = now.duration_since(last.time) < undo_merge_window();
+
+            if last.before == before && rapid {
+                last.time = now;
                 return;
             }
         }
@@ -62,42 +106,117 @@ impl Tab {
         self.history.inner.truncate(*len);
         *len += 1;
 
+        // drop the oldest snapshots
+        let excess = len.saturating_sub(undo_depth().max(1));
+        *len -= excess;
+
         let snapshot = Snapshot {
-            raw: self.raw_snapshot(),
+            cursors: self.cursors.clone(),
+            scroll: (self.v_scroll, self.h_scroll),
+            edits: Vec::new(),
             before,
+            time: now,
         };
 
+        self.history.inner.drain(..excess);
         self.history.inner.push(snapshot);
         self.history.pre_undo.take();
     }
 
     pub fn prepare_insertion(&mut self) {
+        self.forget_goals();
         self.log(Edition::Insertion)
     }
 
     pub fn prepare_deletion(&mut self) {
+        self.forget_goals();
         self.log(Edition::Deletion)
     }
 
-    fn restore_snapshot(&mut self, snapshot: &RawSnapshot) {
-        self.history.len.take();
+    // lines must be changed through this to be undone
+    pub(super) fn line_mut(&mut self, y: usize) -> &mut Line {
+        let line = &mut self.lines[y];
 
-        let mut line = Line::default();
-        line.set_dirty();
+        if let Some(edits) = self.history.edits() {
+            // the state before a run of changes is enough
+            if !matches!(edits.last(), Some(LineEdit::Changed(last, _)) if *last == y) {
+                edits.push(LineEdit::Changed(y, LineText::of(line)));
+            }
+        }
 
-        self.lines.clear();
-        self.lines.push(line);
+        line
+    }
 
-        self.cursors.clear();
-        let insert_cursor = Cursor::new(0);
-        self.cursors.push(insert_cursor);
+    pub(super) fn insert_line(&mut self, y: usize, line: Line) {
+        self.lines.insert(y, line);
 
-        self.insert_text(&snapshot.buffer);
+        if let Some(edits) = self.history.edits() {
+            edits.push(LineEdit::Inserted(y));
+        }
+    }
+
+    pub(super) fn remove_line(&mut self, y: usize) -> Line {
+        let line = self.lines.remove(y);
+
+        if let Some(edits) = self.history.edits() {
+            edits.push(LineEdit::Removed(y, LineText::of(&line)));
+        }
+
+        line
+    }
+
+    fn apply_edit(&mut self, edit: &mut LineEdit) {
+        match edit {
+            LineEdit::Changed(y, text) => {
+                let line = &mut self.lines[*y];
+                swap(&mut line.buffer, &mut text.buffer);
+                swap(&mut line.eol_cr, &mut text.eol_cr);
+                line.set_dirty();
+            },
+            LineEdit::Inserted(y) => {
+                let y = *y;
+                let line = self.lines.remove(y);
+                self.shift_lines(y, -1);
+
+                let text = LineText {
+                    buffer: line.buffer,
+                    eol_cr: line.eol_cr,
+                };
 
-        self.cursors.clear();
-        self.cursors.extend_from_slice(&snapshot.cursors);
+                *edit = LineEdit::Removed(y, text);
+            },
+            LineEdit::Removed(y, text) => {
+                let y = *y;
+                let mut line = Line {
+                    buffer: take(&mut text.buffer),
+                    eol_cr: text.eol_cr,
+                    ..Line::default()
+                };
 
-        self.highlight();
+                line.set_dirty();
+                self.lines.insert(y, line);
+                self.shift_lines(y, 1);
+                *edit = LineEdit::Inserted(y);
+            },
+        }
+    }
+
+    // applies the edits of snapshot `i`
+    fn walk_edits(&mut self, i: usize, backwards: bool) {
+        let mut edits = take(&mut self.history.inner[i].edits);
+
+        match backwards {
+            true => edits.iter_mut().rev().for_each(|edit| self.apply_edit(edit)),
+            false => edits.iter_mut().for_each(|edit| self.apply_edit(edit)),
+        }
+
+        self.history.inner[i].edits = edits;
+    }
+
+    fn restore_state(&mut self, cursors: Vec<Cursor>, scroll: (usize, usize)) {
+        self.cursors = cursors;
+        (self.v_scroll, self.h_scroll) = scroll;
+        self.set_lines_redraw_from(0);
     }
 
     pub fn undo(&mut self) {
@@ -110,23 +229,215 @@ impl Tab {
         };
 
         if self.history.pre_undo.is_none() {
-            self.history.pre_undo = Some(self.raw_snapshot());
+            let scroll = (self.v_scroll, self.h_scroll);
+            self.history.pre_undo = Some((self.cursors.clone(), scroll));
         }
 
-        let snapshot = take(&mut self.history.inner[last].raw);
-        self.restore_snapshot(&snapshot);
+        self.walk_edits(last, true);
 
-        self.history.inner[last].raw = snapshot;
+        let snapshot = &self.history.inner[last];
+        self.restore_state(snapshot.cursors.clone(), snapshot.scroll);
         self.history.len = Some(last);
     }
 
+    pub fn reload(&mut self, text: &str) {
+        // can be undone
+        self.prepare_deletion();
+
+        // folds only survive if lines weren't added or removed
+        let num_lines = self.lines.len();
+        let folds = take(&mut self.folds);
+        let mut bookmarks = take(&mut self.bookmarks);
+        let cursors = take(&mut self.cursors);
+
+        for y in (1..num_lines).rev() {
+            self.remove_line(y);
+        }
+
+        let first = self.line_mut(0);
+        first.buffer.clear();
+        first.eol_cr = false;
+        first.set_dirty();
+
+        self.cursors.push(Cursor::new(0));
+        self.insert_text_cursor(0, text);
+        self.restore_state(cursors, (self.v_scroll, self.h_scroll));
+        self.modified = false;
+
+        if self.lines.len() == num_lines {
+            self.folds = folds;
+            self.folds_dirty = true;
+        }
+
+        bookmarks.retain(|y| *y < self.lines.len());
+        self.bookmarks = bookmarks;
+
+        // keep cursors where they were, if possible
+        let last_y = self.lines.len() - 1;
+
+        for cursor in self.cursors.iter_mut() {
+            cursor.y = cursor.y.min(last_y);
+            cursor.x = cursor.x.min(self.lines[cursor.y].len_chars());
+            cursor.sel_x = 0;
+            cursor.sel_y = 0;
+        }
+
+        self.check_cursors();
+    }
+
     pub fn redo(&mut self) {
-        let Some(snapshot) = self.history.pre_undo.take() else {
+        let Some((cursors, scroll)) = self.history.pre_undo.take() else {
             return;
         };
 
-        self.restore_snapshot(&snapshot);
+        let len = self.history.len.unwrap_or(0);
         let actual_len = self.history.inner.len();
+
+        for i in len..actual_len {
+            self.walk_edits(i, false);
+        }
+
+        self.restore_state(cursors, scroll);
         self.history.len = Some(actual_len);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(tab: &mut Tab) -> String {
+        tab.save().to_string()
+    }
+
+    #[test]
+    fn undo_redo_walk_through_groups() {
+        let mut tab = Tab::new(None, None, "héllo\r\nwörld".into());
+        let mut states = vec![text(&mut tab)];
+
+        // kinds alternate, so that each edit is a group
+        tab.goto(2, 0);
+        tab.insert_text("ç\n");
+        states.push(text(&mut tab));
+        tab.backspace_once(false);
+        states.push(text(&mut tab));
+        tab.insert_text("€\r\nx");
+        states.push(text(&mut tab));
+        tab.backspace_once(true);
+        states.push(text(&mut tab));
+
+        for state in states.iter().rev().skip(1) {
+            tab.undo();
+            assert_eq!(&text(&mut tab), state);
+        }
+
+        // nothing left to undo
+        tab.undo();
+        assert_eq!(text(&mut tab), states[0]);
+
+        tab.redo();
+        assert_eq!(&text(&mut tab), states.last().unwrap());
+    }
+
+    #[test]
+    fn edits_after_undo_drop_redo() {
+        let mut tab = Tab::new(None, None, "abc".into());
+        tab.goto(3, 0);
+        tab.insert_text("d");
+        tab.backspace_once(false);
+        tab.undo();
+        assert_eq!(text(&mut tab), "abcd");
+
+        tab.insert_text("é");
+        tab.redo();
+        assert_eq!(text(&mut tab), "abcdé");
+
+        // merged with the first insertion
+        tab.undo();
+        assert_eq!(text(&mut tab), "abc");
+    }
+
+    #[test]
+    fn groups_only_keep_touched_lines() {
+        let lines: Vec<_> = (0..1000).map(|i| format!("line {i}")).collect();
+        let original = lines.join("\n");
+        let mut tab = Tab::new(None, None, original.clone());
+
+        // edits at the top and at the bottom of the file
+        tab.goto(0, 999);
+        tab.add_cursor_vertical(true);
+        tab.cursors[0] = Cursor { x: 0, y: 0, ..tab.cursors[0] };
+        tab.insert_text("ü\n");
+
+        let edits = &tab.history.inner.last().unwrap().edits;
+        assert!(edits.len() <= 6, "{edits:?}");
+
+        tab.undo();
+        assert_eq!(text(&mut tab), original);
+    }
+
+    #[test]
+    fn reload_and_moved_lines_can_be_undone() {
+        let mut tab = Tab::new(None, None, "a\nb\nc".into());
+        tab.reload("x\ny");
+        assert_eq!(text(&mut tab), "x\ny");
+        tab.undo();
+        assert_eq!(text(&mut tab), "a\nb\nc");
+
+        tab.goto(0, 2);
+        tab.move_lines(true);
+        assert_eq!(text(&mut tab), "a\nc\nb");
+        tab.undo();
+        assert_eq!(text(&mut tab), "a\nb\nc");
+        tab.redo();
+        assert_eq!(text(&mut tab), "a\nc\nb");
+    }
+
+    #[test]
+    fn overtyped_selections_are_one_group() {
+        let mut tab = Tab::new(None, None, "abcd".into());
+        tab.toggle_overtype();
+        tab.cursors[0] = Cursor { x: 3, sel_x: -2, ..tab.cursors[0] };
+        tab.insert_char('x');
+        tab.insert_char('y');
+        assert_eq!(text(&mut tab), "axy");
+
+        tab.undo();
+        assert_eq!(text(&mut tab), "abcd");
+        assert_eq!(tab.history.len, Some(0));
+    }
+
+    #[test]
+    fn dedenting_nothing_is_not_logged() {
+        let mut tab = Tab::new(None, None, "a\n  b".into());
+        tab.cursors[0] = Cursor { x: 1, y: 1, sel_y: -1, ..tab.cursors[0] };
+        tab.indent_selection(true);
+        assert_eq!(text(&mut tab), "a\nb");
+
+        // nothing left to remove
+        tab.modified = false;
+        tab.indent_selection(true);
+        assert!(!tab.modified);
+        assert_eq!(tab.history.len, Some(1));
+    }
+
+    #[test]
+    fn applied_edits_become_their_inverse() {
+        let mut tab = Tab::new(None, None, "ä\nö".into());
+        let mut edit = LineEdit::Inserted(1);
+
+        tab.apply_edit(&mut edit);
+        assert_eq!(text(&mut tab), "ä");
+
+        let expected = LineText {
+            buffer: "ö".into(),
+            eol_cr: false,
+        };
+
+        assert_eq!(edit, LineEdit::Removed(1, expected));
+
+        tab.apply_edit(&mut edit);
+        assert_eq!(text(&mut tab), "ä\nö");
+        assert_eq!(edit, LineEdit::Inserted(1));
+    }
+}
@@ -41,18 +41,17 @@ impl Tab {
     fn insert_text_no_lf(&mut self, c: usize, text: &str) {
         let cursor = self.cursors[c];
 
-        let line = &mut self.lines[cursor.y];
+        let line = self.line_mut(cursor.y);
         let offset = line.len_until(cursor.x);
         line.buffer.insert_str(offset, text);
         line.set_dirty();
 
-        self.check_line_highlighting(cursor.y);
-        self.add_to_cursors(c, false, text.len());
+        self.add_to_cursors(c, false, text.chars().count());
     }
 
     fn line_feed(&mut self, c: usize, mut eol_cr: bool) {
-        let cursor = &self.cursors[c];
-        let line = &mut self.lines[cursor.y];
+        let cursor = self.cursors[c];
+        let line = self.line_mut(cursor.y);
 
         swap(&mut line.eol_cr, &mut eol_cr);
         let offset = line.len_until(cursor.x);
@@ -62,17 +61,25 @@ impl Tab {
         let new_line = Line {
             buffer,
             ranges: vec![],
+            bol_ctx: None,
             eol_ctx: None,
+            bol_depth: 0,
+            eol_depth: 0,
             must_highlight: true,
             must_draw: true,
             eol_cr,
         };
 
         let old_y = cursor.y;
-        self.lines.insert(old_y + 1, new_line);
+        self.insert_line(old_y + 1, new_line);
+
+        // at the start of a line, its text moves down
+        let shifted_y = old_y + (offset > 0) as usize;
+        self.shift_lines(shifted_y, 1);
 
         self.add_to_cursors(c, true, 0);
-        self.set_lines_dirty(old_y);
+        self.lines[old_y].set_dirty();
+        self.set_lines_redraw_from(old_y + 1);
     }
 
     pub(super) fn insert_text_cursor(&mut self, c: usize, text: &str) {
@@ -110,24 +117,413 @@ impl Tab {
     pub fn insert_char(&mut self, c: char) {
         let mut buf = [0u8; 4];
         let text = c.encode_utf8(&mut buf);
-        self.insert_text(text);
+
+        if !self.overtype || c == '\n' {
+            return self.insert_text(text);
+        }
+
+        // selections are replaced as usual
+        let overwrite: Vec<_> = self.cursors.iter().map(|c| !c.selects()).collect();
+
+        // logged as an insertion only, so that a
+        // run of keystrokes is undone at once
+        self.prepare_insertion();
+
+        if self.has_selections() {
+            self.erase_selection_unlogged();
+        }
+
+        for (c, overwrite) in overwrite.into_iter().enumerate() {
+            if overwrite {
+                self.overwrite_char(c);
+            }
+
+            self.insert_text_cursor(c, text);
+        }
+
+        self.modified = true;
+    }
+
+    // removes the char under a cursor, if it isn't at the end of its line
+    fn overwrite_char(&mut self, c: usize) {
+        let Cursor { x, y, .. } = self.cursors[c];
+
+        if x == self.lines[y].len_chars() {
+            return;
+        }
+
+        let line = self.line_mut(y);
+        let offset = line.len_until(x);
+        let len = line.buffer[offset..].chars().next().map_or(0, char::len_utf8);
+        line.buffer.replace_range(offset..offset + len, "");
+        line.set_dirty();
+
+        self.shift_cursors_in_line(y, x, -1);
     }
 
     pub fn insert_tab(&mut self) {
+        if self.cursors.iter().any(|c| c.sel_y != 0) {
+            return self.indent_selection(false);
+        }
+
         let tab_string = take(&mut self.tab_string);
         self.insert_text(&tab_string);
         self.tab_string = tab_string;
     }
 
+    // adds or removes one indent level on touched lines
+    pub fn indent_selection(&mut self, dedent: bool) {
+        let width = self.tab_width_m1 + 1;
+        let tab_string = self.tab_string.clone();
+        let indent_len = tab_string.chars().count();
+
+        let removable = |line: &Line| match line.buffer.starts_with('\t') {
+            true => 1,
+            false => line.buffer.chars().take(width).take_while(|c| *c == ' ').count(),
+        };
+
+        // cursors at the start of lines stay there
+        let changes: Vec<_> = self.touched_lines().into_iter().filter_map(|y| {
+            let line = &self.lines[y];
+
+            let (from_x, delta) = match dedent {
+                true => (0, -(removable(line) as isize)),
+                false => (1, !line.buffer.is_empty() as isize * indent_len as isize),
+            };
+
+            (delta != 0).then_some((y, from_x, delta))
+        }).collect();
+
+        if changes.is_empty() {
+            return;
+        }
+
+        match dedent {
+            true => self.prepare_deletion(),
+            false => self.prepare_insertion(),
+        }
+
+        for (y, from_x, delta) in changes {
+            let line = self.line_mut(y);
+
+            // indentation is ascii: byte and char lengths are equal
+            match dedent {
+                true => line.buffer.replace_range(..-delta as usize, ""),
+                false => line.buffer.insert_str(0, &tab_string),
+            }
+
+            line.set_dirty();
+            self.shift_cursors_in_line(y, from_x, delta);
+        }
+
+        self.modified = true;
+    }
+
+    pub fn transform_case(&mut self, mode: CaseMode) {
+        if !self.has_selections() {
+            return;
+        }
+
+        let mut texts = Vec::with_capacity(self.cursors.len());
+
+        for c in 0..self.cursors.len() {
+            let mut text = String::new();
+            self.extract_selection(c, &mut text);
+
+            let text = match mode {
+                CaseMode::Upper => text.to_uppercase(),
+                CaseMode::Lower => text.to_lowercase(),
+                CaseMode::Toggle => text.chars().fold(String::new(), |mut acc, c| {
+                    match c.is_lowercase() {
+                        true => acc.extend(c.to_uppercase()),
+                        false => acc.extend(c.to_lowercase()),
+                    }
+
+                    acc
+                }),
+            };
+
+            texts.push(text);
+        }
+
+        self.prepare_insertion();
+        self.erase_selection();
+
+        for (c, text) in texts.iter().enumerate() {
+            let Cursor { x, y, .. } = self.cursors[c];
+            self.insert_text_cursor(c, text);
+
+            // select the transformed text again
+            let cursor = &mut self.cursors[c];
+            cursor.sel_x = x as isize - cursor.x as isize;
+            cursor.sel_y = y as isize - cursor.y as isize;
+        }
+
+        self.modified = true;
+    }
+
+    // rewrites leading indentation to match `tab_string`
+    pub fn retab(&mut self) {
+        // string contexts are needed for every line
+        self.highlight();
+
+        let width = self.tab_width_m1 + 1;
+        let hard_tabs = self.tab_string == "\t";
+        let mut changes = Vec::new();
+
+        for y in 0..self.lines.len() {
+            let prev_ctx = y.checked_sub(1).and_then(|p| self.lines[p].eol_ctx);
+
+            // this line starts inside a string
+            if matches!(prev_ctx, Some(LineContext::String(..) | LineContext::Special(..))) {
+                continue;
+            }
+
+            let buffer = &self.lines[y].buffer;
+            let indent = buffer.len() - buffer.trim_start_matches([' ', '\t']).len();
+
+            let cell_width = |c| match c {
+                '\t' => width,
+                _ => 1,
+            };
+
+            let cells: usize = buffer[..indent].chars().map(cell_width).sum();
+
+            let new_indent = match hard_tabs {
+                true => "\t".repeat(cells / width) + &" ".repeat(cells % width),
+                false => " ".repeat(cells),
+            };
+
+            if new_indent != buffer[..indent] {
+                changes.push((y, indent, new_indent));
+            }
+        }
+
+        if changes.is_empty() {
+            return;
+        }
+
+        self.prepare_insertion();
+
+        for (y, old_len, new_indent) in changes {
+            // indentation is ascii: byte and char lengths are equal
+            let new_len = new_indent.len();
+            self.line_mut(y).buffer.replace_range(..old_len, &new_indent);
+
+            let shift = |x: usize| match x < old_len {
+                true => x.min(new_len),
+                false => x - old_len + new_len,
+            };
+
+            for cursor in self.cursors.iter_mut() {
+                let origin_y = cursor.y as isize + cursor.sel_y;
+                let mut origin_x = (cursor.x as isize + cursor.sel_x) as usize;
+
+                if origin_y == y as isize {
+                    origin_x = shift(origin_x);
+                }
+
+                if cursor.y == y {
+                    cursor.x = shift(cursor.x);
+                }
+
+                cursor.sel_x = origin_x as isize - cursor.x as isize;
+            }
+        }
+
+        self.set_lines_dirty(0);
+        self.check_cursors();
+        self.modified = true;
+    }
+
+    // most line feeds are preceded by a carriage return
+    pub fn uses_crlf(&self) -> bool {
+        let line_feeds = self.lines.len() - 1;
+        let crlf = self.lines.iter().filter(|line| line.eol_cr).count();
+        crlf * 2 > line_feeds
+    }
+
+    // the last line has no line feed
+    pub fn convert_eol(&mut self, crlf: bool) {
+        let last = self.lines.len() - 1;
+
+        if self.lines[..last].iter().all(|line| line.eol_cr == crlf) {
+            return;
+        }
+
+        self.prepare_insertion();
+
+        for y in 0..last {
+            if self.lines[y].eol_cr != crlf {
+                self.line_mut(y).eol_cr = crlf;
+            }
+        }
+
+        self.modified = true;
+    }
+
+    // moves cursors and selection origins from column `from_x` of line `y`
+    pub(super) fn shift_cursors_in_line(&mut self, y: usize, from_x: usize, delta: isize) {
+        let shift = |x: usize| match x < from_x {
+            true => x,
+            false => x.saturating_add_signed(delta).max(from_x),
+        };
+
+        for cursor in self.cursors.iter_mut() {
+            let origin_y = cursor.y as isize + cursor.sel_y;
+            let mut origin_x = (cursor.x as isize + cursor.sel_x) as usize;
+
+            if origin_y == y as isize {
+                origin_x = shift(origin_x);
+            }
+
+            if cursor.y == y {
+                cursor.x = shift(cursor.x);
+            }
+
+            cursor.sel_x = origin_x as isize - cursor.x as isize;
+        }
+    }
+
+    pub fn toggle_comment(&mut self) {
+        let Some(syntax) = self.syntax.clone() else {
+            alert!("Cannot toggle comments: Tab has no syntax.");
+            return;
+        };
+
+        let Some(prefix) = syntax.comment_prefix() else {
+            alert!("Cannot toggle comments: Syntax has no comment prefix.");
+            return;
+        };
+
+        let prefix_chars = prefix.chars().count();
+        self.prepare_insertion();
+
+        for y in self.touched_lines() {
+            let line = self.line_mut(y);
+            let indent = indent_len(&line.buffer);
+            let rest = &line.buffer[indent..];
+
+            let delta = if let Some(payload) = rest.strip_prefix(prefix) {
+                let space = payload.starts_with(' ') as usize;
+                let stop = indent + prefix.len() + space;
+                line.buffer.replace_range(indent..stop, "");
+                -((prefix_chars + space) as isize)
+            } else if !rest.is_empty() {
+                line.buffer.insert(indent, ' ');
+                line.buffer.insert_str(indent, prefix);
+                (prefix_chars + 1) as isize
+            } else {
+                continue;
+            };
+
+            let x = line.buffer[..indent].chars().count();
+            line.set_dirty();
+
+            self.shift_cursors_in_line(y, x, delta);
+        }
+
+        self.modified = true;
+    }
+
+    pub fn align(&mut self) {
+        let Some(input) = prompt!("Align the selected lines on which character?\n\n") else {
+            return;
+        };
+
+        let mut chars = input.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.align_on(c),
+            _other => alert!("Invalid Character"),
+        }
+    }
+
+    // pads lines of the latest selection so that their
+    // first `c` is in the same column
+    pub fn align_on(&mut self, c: char) {
+        let latest = self.latest_cursor();
+        let cursor = self.cursors[latest];
+        let first_y = cursor.y.saturating_add_signed(cursor.sel_y).min(cursor.y);
+
+        let mut selection = String::new();
+        self.extract_selection(latest, &mut selection);
+        let num_lines = selection.split('\n').count();
+
+        // (line, char index, cells before it)
+        let mut targets = Vec::new();
+
+        for y in first_y..first_y + num_lines {
+            let line = &self.lines[y];
+
+            if let Some(x) = line.buffer.chars().position(|d| d == c) {
+                targets.push((y, x, line.cells_until(x, self.tab_width_m1)));
+            }
+        }
+
+        let column = targets.iter().map(|(_, _, cells)| *cells).max();
+        let Some(column) = column.filter(|_| targets.len() > 1) else {
+            return;
+        };
+
+        if targets.iter().all(|(_, _, cells)| *cells == column) {
+            return;
+        }
+
+        self.prepare_insertion();
+
+        for (y, x, cells) in targets {
+            let padding = column - cells;
+
+            if padding == 0 {
+                continue;
+            }
+
+            let line = self.line_mut(y);
+            let offset = line.len_until(x);
+            line.buffer.insert_str(offset, &" ".repeat(padding));
+            line.set_dirty();
+
+            self.shift_cursors_in_line(y, x, padding as isize);
+        }
+
+        self.modified = true;
+    }
+
+    pub fn replace_all(&mut self, needle: &str, replacement: &str) {
+        #[cfg(feature = "regex")]
+        match pattern::parse_regex(needle) {
+            Ok(Some(re)) => return self.replace_regex(&re, replacement),
+            Ok(None) => (),
+            Err(error) => return alert!("Invalid pattern:\n{error}"),
+        }
+
+        if needle.is_empty() || self.locate(needle) == 0 {
+            return;
+        }
+
+        // one cursor per match, leaving them for review
+        self.insert_text(replacement);
+    }
+
+    pub fn find_replace(&mut self) {
+        let Some(needle) = prompt!("{}", crate::SEARCH_PROMPT) else {
+            return;
+        };
+
+        if let Some(replacement) = prompt!("{}", crate::REPLACE_PROMPT) {
+            self.replace_all(&needle, &replacement);
+        }
+    }
+
     pub fn smart_carriage_return(&mut self) {
         const CAP: usize = 64;
 
         let c = self.latest_cursor();
         let index = self.cursors[c].y;
-        let line = &self.lines[index];
-
-        let crlf_i = line.eol_cr as usize;
+        let crlf_i = self.uses_crlf() as usize;
         let crlf = ["\n", "\r\n"][crlf_i];
+        let line = &self.lines[index];
 
         let indent_len = indent_len(&line.buffer);
         let indent = &line.buffer[..indent_len];
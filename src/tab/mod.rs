@@ -3,34 +3,76 @@ use std::fmt::Write;
 use std::sync::Arc;
 use std::{fs, cmp};
 
-use crate::interface::colored_text::{Part as TextPart, Selection};
-use crate::syntax::{Range, SyntaxFile, SyntaxConfig, LineContext};
+use crate::interface::colored_text::{Part as TextPart, Segment, Selection, char_width};
+use crate::syntax::{Range, RangeMode, SyntaxFile, SyntaxConfig, LineContext, color_brackets};
 use crate::{alert, confirm, prompt};
 use crate::forest::FileKey;
+use crate::config::{eol_selection, bracket_colors};
 
 use history::History;
 
+pub use clipboard::remove_clipboard_file;
+
 mod rendering;
 mod clipboard;
 mod insertion;
 mod deletion;
 mod movement;
 mod history;
-
-const CLOSE_WARNING: &str = "[UNSAVED FILE]\nReally close This file? It has unsaved edits!";
+mod brackets;
+mod bookmarks;
+mod folding;
+mod numbers;
+mod search;
+
+#[cfg(feature = "regex")]
+mod pattern;
+
+const INDENT_SCAN_LINES: usize = 256;
+// files above this size are opened read-only,
+// without history nor highlighting
+const LARGE_FILE_SIZE: usize = 32 << 20;
+// shown next to line numbers
+const FOLD_MARK: char = '⋯';
+const BOOKMARK_MARK: char = '•';
+// shown before the names of pinned tabs
+const PIN_MARK: char = '◆';
+const LOCK_MARK: char = '⊘';
+const CLOSE_WARNING: &str = "[UNSAVED FILE]";
 
 pub type TabList = Vec<(bool, Arc<str>)>;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaseMode {
+    Upper,
+    Lower,
+    Toggle,
+}
+
 #[derive(Clone, Debug, Default)]
 struct Line {
     buffer: String,
     ranges: Vec<Range>,
+    // context the ranges were computed with
+    bol_ctx: Option<LineContext>,
     eol_ctx: Option<LineContext>,
+    // bracket nesting, for rainbow brackets
+    bol_depth: usize,
+    eol_depth: usize,
     eol_cr: bool,
     must_highlight: bool,
     must_draw: bool,
 }
 
+// a screen row when soft wrapping
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct Row {
+    line: usize,
+    start: usize,
+    // none for the last row of a line
+    end: Option<usize>,
+}
+
 pub struct DirtyLine<'a> {
     pub horizontal_scroll: usize,
     pub tab_width_m1: usize,
@@ -40,16 +82,37 @@ pub struct DirtyLine<'a> {
 pub struct Tab {
     file_key: Option<FileKey>,
     tmp_buf: String,
-    internal_clipboard: String,
+    line_copy: String,
     name: Arc<str>,
     lines: Vec<Line>,
     v_scroll: usize,
     h_scroll: usize,
     cursors: Vec<Cursor>,
+    // (line, cells) where a block selection started
+    block_anchor: Option<(usize, usize)>,
+    bracket_match: Option<[(usize, usize); 2]>,
+    search_backup: Option<(Vec<Cursor>, usize)>,
+    found: Vec<(usize, usize, usize)>,
     modified: bool,
     syntax: Option<Arc<SyntaxConfig>>,
     tab_width_m1: usize,
     tab_string: String,
+    indent_detected: bool,
+    read_only: bool,
+    // invalid bytes were replaced when opening
+    lossy: bool,
+    // not replaced by opened files nor closed in bulk
+    pinned: bool,
+    overtype: bool,
+    wrap: bool,
+    wrap_width: usize,
+    // (row, must draw) of each screen row, when wrapping or folding
+    rows: Vec<(Row, bool)>,
+    // (header, last line) of folded blocks
+    folds: Vec<(usize, usize)>,
+    folds_dirty: bool,
+    // sorted line indices
+    bookmarks: Vec<usize>,
     history: History,
 }
 
@@ -66,13 +129,26 @@ struct Cursor {
     sel_x: isize,
     sel_y: isize,
     id: usize,
+    // (cells, x, y): column kept by vertical jumps,
+    // as long as the cursor stays where the last one left it
+    goal: Option<(usize, usize, usize)>,
 }
 
 impl Cursor {
     fn new(id: usize) -> Self {
-        Self { x: 0, y: 0, sel_x: 0, sel_y: 0, id }
+        Self { x: 0, y: 0, sel_x: 0, sel_y: 0, id, goal: None }
     }
 
+    // cells to aim at when moving to another line
+    fn goal_cells(&self, line: &Line, tab_width_m1: usize) -> usize {
+        match self.goal {
+            Some((cells, x, y)) if (x, y) == (self.x, self.y) => cells,
+            _ => line.cells_until(self.x, tab_width_m1),
+        }
+    }
+
+    // line `y` lies strictly between the cursor and its selection
+    // origin, whichever of them is above the other
     fn covers(&self, y: usize) -> bool {
         match self.y.cmp(&y) {
             cmp::Ordering::Greater => self.sel_y < -((self.y - y) as isize),
@@ -81,6 +157,7 @@ impl Cursor {
         }
     }
 
+    // the selection origin is on line `y`, and the cursor isn't
     fn touches(&self, y: usize) -> bool {
         let diff = (y as isize) - (self.y as isize);
         self.sel_y != 0 && diff == self.sel_y
@@ -121,6 +198,11 @@ impl Line {
         self.buffer.chars().count()
     }
 
+    fn indent_chars(&self) -> usize {
+        let is_indent = |c: &char| c.is_whitespace();
+        self.buffer.chars().take_while(is_indent).count()
+    }
+
     fn len_until(&self, x: usize) -> usize {
         self
             .buffer
@@ -130,21 +212,39 @@ impl Line {
             .sum()
     }
 
+    // the top line of a selection gets the second half,
+    // and its bottom line gets the first half
     fn half_select(&self, first_half: bool, x_char: usize) -> Selection {
+        // the second half also selects the line feed
+        let eol = eol_selection() as usize;
+
         match first_half {
             true => Selection::new(0, x_char),
-            false => Selection::new(x_char, self.len_chars() - x_char),
+            false => Selection::new(x_char, self.len_chars() - x_char + eol),
         }
     }
 
     fn cells_until(&self, char_x: usize, tab_width_m1: usize) -> usize {
-        let char_width = |c| match c {
-            '\t' => tab_width_m1 + 1,
-            _ => 1,
-        };
-
         let i = self.len_until(char_x);
-        self.buffer[..i].chars().map(char_width).sum()
+        self.buffer[..i].chars().map(|c| char_width(c, tab_width_m1)).sum()
+    }
+
+    // first char of each row taken by this line when wrapped
+    fn wrap_starts(&self, width: usize, tab_width_m1: usize, dst: &mut Vec<usize>) {
+        let mut cells = 0;
+        dst.clear();
+        dst.push(0);
+
+        for (i, c) in self.buffer.chars().enumerate() {
+            let char_width = char_width(c, tab_width_m1);
+
+            if cells > 0 && cells + char_width > width {
+                dst.push(i);
+                cells = 0;
+            }
+
+            cells += char_width;
+        }
     }
 
     fn set_dirty(&mut self) {
@@ -153,6 +253,53 @@ impl Line {
     }
 }
 
+// (hard tabs?, width of soft tabs) of the dominant indentation
+fn detect_indent(text: &str) -> (Option<bool>, Option<usize>) {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut prev_spaces = 0;
+
+    // occurrences of indent steps of 8, 2 & 4 spaces
+    let mut steps = [(8, 0), (2, 0), (4, 0)];
+
+    let not_blank = |line: &&str| !line.trim().is_empty();
+
+    for line in text.lines().filter(not_blank).take(INDENT_SCAN_LINES) {
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        space_lines += (spaces > 0) as usize;
+
+        if let Some(delta) = spaces.checked_sub(prev_spaces) {
+            if let Some(step) = steps.iter_mut().find(|(w, _)| *w == delta) {
+                step.1 += 1;
+            }
+        }
+
+        prev_spaces = spaces;
+    }
+
+    if tab_lines + space_lines == 0 {
+        return (None, None);
+    }
+
+    if tab_lines > space_lines {
+        return (Some(true), None);
+    }
+
+    // ties favor the last candidate
+    let width = steps
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .max_by_key(|(_, n)| *n)
+        .map(|(w, _)| *w);
+
+    (Some(false), width)
+}
+
 impl Tab {
     fn new(
         syntax: Option<Arc<SyntaxConfig>>,
@@ -160,11 +307,24 @@ impl Tab {
         text: String,
     ) -> Self {
         let name = file_name(&file_key);
+        let read_only = text.len() > LARGE_FILE_SIZE;
+        let syntax = syntax.filter(|_| !read_only);
         let mut line = Line::default();
         line.must_draw = true;
 
-        let tab_width_m1 = 3;
-        let has_hard_tabs = text.contains('\t');
+        // the syntax may specify an indent policy
+        let indent_width = syntax.as_ref().and_then(|s| s.indent_width());
+        let hard_tabs = syntax.as_ref().and_then(|s| s.hard_tabs());
+
+        let (detected_hard_tabs, detected_width) = detect_indent(&text);
+        let indent_detected = match hard_tabs {
+            Some(_) => indent_width.is_none() && detected_width.is_some(),
+            None => detected_hard_tabs.is_some(),
+        };
+
+        let indent_width = indent_width.or(detected_width);
+        let tab_width_m1 = indent_width.unwrap_or(4).saturating_sub(1);
+        let has_hard_tabs = hard_tabs.or(detected_hard_tabs).unwrap_or(false);
         let tab_string = match has_hard_tabs {
             true => String::from("\t"),
             false => " ".repeat(tab_width_m1 + 1),
@@ -177,17 +337,36 @@ impl Tab {
             lines: vec![line],
             v_scroll: 0,
             h_scroll: 0,
-            internal_clipboard: String::new(),
+            line_copy: String::new(),
             cursors: vec![Cursor::new(0)],
+            block_anchor: None,
+            bracket_match: None,
+            search_backup: None,
+            found: Vec::new(),
             modified: false,
             tab_width_m1,
             tab_string,
+            indent_detected,
             syntax,
+            read_only,
+            lossy: false,
+            pinned: false,
+            overtype: false,
+            wrap: false,
+            wrap_width: 1,
+            rows: Vec::new(),
+            folds: Vec::new(),
+            folds_dirty: false,
+            bookmarks: Vec::new(),
             history: History::new(),
         };
 
         this.insert_text(&text);
-        this.history.activate();
+
+        if !read_only {
+            this.history.activate();
+        }
+
         this.modified = false;
         this.tmp_buf = text;
 
@@ -197,13 +376,56 @@ impl Tab {
     }
 
     fn header(&self) -> (bool, Arc<str>) {
-        (self.modified, self.name.clone())
+        let marks = [(self.pinned, PIN_MARK), (self.read_only, LOCK_MARK)];
+        let mut name = String::new();
+
+        for (_, mark) in marks.iter().filter(|(shown, _)| *shown) {
+            name.push(*mark);
+            name.push(' ');
+        }
+
+        match name.is_empty() {
+            true => (self.modified, self.name.clone()),
+            false => (self.modified, (name + &self.name).into()),
+        }
+    }
+
+    pub fn toggle_pin(&mut self) {
+        self.pinned = !self.pinned;
     }
 
     pub fn modified(&mut self) -> &mut bool {
         &mut self.modified
     }
 
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn toggle_read_only(&mut self) {
+        if self.lossy {
+            alert!("This tab must stay read-only: invalid bytes were replaced when opening it.");
+            return;
+        }
+
+        self.read_only = !self.read_only;
+
+        // large files start without history
+        if !self.read_only && !self.history.is_active() {
+            self.history.activate();
+        }
+    }
+
+    pub fn lossy(&self) -> bool {
+        self.lossy
+    }
+
+    // saving would write replacement chars back
+    pub fn set_lossy(&mut self) {
+        self.lossy = true;
+        self.read_only = true;
+    }
+
     pub fn has_selections(&self) -> bool {
         self.cursors.iter().any(Cursor::selects)
     }
@@ -217,6 +439,42 @@ impl Tab {
         (y < self.lines.len()).then_some(y)
     }
 
+    // screen rows don't map to lines one to one
+    fn uses_rows(&self) -> bool {
+        self.wrap || !self.folds.is_empty()
+    }
+
+    // (line index, first char shown) of a screen row
+    fn screen_line(&self, screen_y: u16) -> Option<(usize, usize)> {
+        match self.uses_rows() {
+            true => self.rows.get(screen_y as usize).map(|(row, _)| (row.line, row.start)),
+            false => Some((self.line_index(screen_y)?, 0)),
+        }
+    }
+
+    // (line index, cells before x) of a screen position
+    fn screen_to_line(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        let (y, start) = self.screen_line(y)?;
+
+        let cells = match self.wrap {
+            true => self.lines[y].cells_until(start, self.tab_width_m1) + x as usize,
+            false => x as usize + self.h_scroll.saturating_sub(1),
+        };
+
+        Some((y, cells))
+    }
+
+    pub fn toggle_overtype(&mut self) {
+        self.overtype = !self.overtype;
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.h_scroll = 0;
+        self.rows.clear();
+        self.set_lines_redraw();
+    }
+
     pub fn set_lines_redraw(&mut self) {
         for line in self.lines.iter_mut() {
             line.must_draw = true;
@@ -229,10 +487,37 @@ impl Tab {
         }
     }
 
+    // `delta` lines were inserted (or removed) at `y`
+    fn shift_lines(&mut self, y: usize, delta: isize) {
+        self.shift_folds(y, delta);
+        self.shift_bookmarks(y, delta);
+    }
+
+    pub fn gutter_mark(&self, y: usize) -> char {
+        if self.is_fold_header(y) {
+            FOLD_MARK
+        } else if self.is_bookmarked(y) {
+            BOOKMARK_MARK
+        } else {
+            ' '
+        }
+    }
+
+    // lines below an edit only move on screen
+    fn set_lines_redraw_from(&mut self, from_line: usize) {
+        self.folds_dirty = true;
+
+        for line in self.lines.iter_mut().skip(from_line) {
+            line.must_draw = true;
+        }
+    }
+
     pub fn set_fully_dirty(&mut self) {
         self.set_lines_dirty(0);
     }
 
+    // a final line feed is kept as an empty last line,
+    // so files without one are written back without one
     fn rebuild(&mut self) {
         self.tmp_buf.clear();
 
@@ -251,15 +536,83 @@ impl Tab {
         }
     }
 
+    // cursors at the same place would edit it twice:
+    // they are merged, keeping the latest one
     pub fn check_cursors(&mut self) {
+        let len = self.cursors.len();
         self.cursors.sort();
-        self.cursors.dedup();
+
+        self.cursors.dedup_by(|next, kept| {
+            let same = (next.x, next.y) == (kept.x, kept.y);
+
+            if same && next.id > kept.id {
+                *kept = *next;
+            }
+
+            same
+        });
+
+        if self.cursors.len() == len {
+            return;
+        }
+
+        // new cursors take the count as id
+        let mut order: Vec<_> = (0..self.cursors.len()).collect();
+        order.sort_by_key(|c| self.cursors[*c].id);
+
+        for (id, c) in order.into_iter().enumerate() {
+            self.cursors[c].id = id;
+        }
     }
 
     pub fn cursor_count(&self) -> usize {
         self.cursors.len()
     }
 
+    pub fn collapse_to_latest(&mut self) {
+        let c = self.latest_cursor();
+        let latest = self.cursors[c];
+
+        for cursor in &self.cursors {
+            self.lines[cursor.y].must_draw = true;
+        }
+
+        self.cursors.clear();
+        self.cursors.push(latest);
+    }
+
+    // sorted by id: the latest cursor comes last
+    pub fn cursor_positions(&self) -> Vec<(usize, usize)> {
+        let mut cursors = self.cursors.clone();
+        cursors.sort_by_key(|c| c.id);
+        cursors.iter().map(|c| (c.x, c.y)).collect()
+    }
+
+    pub fn scroll_offsets(&self) -> (usize, usize) {
+        (self.v_scroll, self.h_scroll)
+    }
+
+    pub fn restore_view(&mut self, cursors: &[(usize, usize)], scroll: (usize, usize)) {
+        let last_line = self.lines.len() - 1;
+        self.cursors.clear();
+
+        for (id, &(x, y)) in cursors.iter().enumerate() {
+            let mut cursor = Cursor::new(id);
+            cursor.y = y.min(last_line);
+            cursor.x = x.min(self.lines[cursor.y].len_chars());
+            self.cursors.push(cursor);
+        }
+
+        if self.cursors.is_empty() {
+            self.cursors.push(Cursor::new(0));
+        }
+
+        self.check_cursors();
+        self.v_scroll = scroll.0.min(last_line);
+        self.h_scroll = scroll.1;
+        self.set_lines_redraw();
+    }
+
     pub fn cursor_desc(&mut self, c: usize, dst: &mut String) {
         let mut sel_count = 0usize;
         self.extract_selection(c, &mut sel_count);
@@ -274,6 +627,50 @@ impl Tab {
         }
     }
 
+    pub fn status(&mut self, dst: &mut String) {
+        let c = self.latest_cursor();
+        let cursor = &self.cursors[c];
+        let (x, y) = (cursor.x + 1, cursor.y + 1);
+
+        let path = match self.file_key.as_ref() {
+            Some(key) => key.path(),
+            None => "[unnamed]",
+        };
+
+        let width = self.tab_width_m1 + 1;
+        let indent = match self.tab_string.as_str() {
+            "\t" => "Tabs",
+            _ => "Spaces",
+        };
+
+        let detected = match self.indent_detected {
+            true => " (detected)",
+            false => "",
+        };
+
+        let syntax = match self.syntax.as_ref() {
+            Some(syntax) => syntax.name(),
+            None => "none",
+        };
+
+        let eol = match self.uses_crlf() {
+            true => "CRLF",
+            false => "LF",
+        };
+
+        let _ = write!(dst, " {path} │ Line {y}, Column {x} │ {indent}: {width}{detected} │ {syntax} │ {eol}");
+
+        if self.lossy {
+            let _ = write!(dst, " │ read-only (lossy)");
+        } else if self.read_only {
+            let _ = write!(dst, " │ read-only");
+        }
+
+        if self.overtype {
+            let _ = write!(dst, " │ overtype");
+        }
+    }
+
     pub fn swap_latest_cursor(&mut self, c: usize) {
         let original = self.latest_cursor();
         let orig_id = self.cursors[original].id;
@@ -299,15 +696,21 @@ impl Tab {
         };
 
         if name == "none" {
-            self.syntax.take();
+            self.set_syntax(None);
         } else {
             let Some(syntax) = syntaxes.get(&name) else {
                 alert!("No Such Syntax");
                 return;
             };
 
-            self.syntax = Some(syntax);
+            self.set_syntax(Some(syntax));
         }
+    }
+
+    // locked tabs are highlighted, only large files aren't
+    fn set_syntax(&mut self, syntax: Option<Arc<SyntaxConfig>>) {
+        let size: usize = self.lines.iter().map(|line| line.buffer.len() + 1).sum();
+        self.syntax = syntax.filter(|_| size <= LARGE_FILE_SIZE);
 
         for line in self.lines.iter_mut() {
             line.ranges.clear();
@@ -315,6 +718,15 @@ impl Tab {
         }
     }
 
+    // the tab was saved to a new file
+    pub fn set_file(&mut self, syntaxes: &SyntaxFile, key: FileKey) {
+        let syntax = resolve_syntax(syntaxes, &key);
+        self.file_key = Some(key);
+        self.name = file_name(&self.file_key);
+        self.set_syntax(syntax);
+        self.modified = false;
+    }
+
     pub fn switch_indent_mode(&mut self) {
         let l1 = "Input an indent policy to apply.";
         let l2 = "It must be formatted as such: '<s/h><width>'.";
@@ -335,6 +747,7 @@ impl Tab {
             return alert!("Invalid Mode");
         };
 
+        self.indent_detected = false;
         self.tab_width_m1 = width - 1;
         self.tab_string = match hard_tabs {
             false => " ".repeat(width),
@@ -371,9 +784,19 @@ impl TabMap {
         &mut self.inner[index]
     }
 
-    pub fn open(&mut self, syntaxes: &SyntaxFile, file: FileKey, text: String) {
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn open(&mut self, syntaxes: &SyntaxFile, file: FileKey, text: String, lossy: bool) {
+        crate::session::push_recent_file(file.path());
+
         let cur_tab = self.current();
-        let replace_current = cur_tab.file_key.is_none() && !cur_tab.modified;
+        let replace_current = cur_tab.file_key.is_none() && !cur_tab.modified && !cur_tab.pinned;
 
         for (index, tab) in self.inner.iter().enumerate() {
             if tab.file_key.as_ref() == Some(&file) {
@@ -382,15 +805,18 @@ impl TabMap {
             }
         }
 
-        let mut syntax = None;
-        if let Some((_, ext)) = file.path().rsplit_once('.') {
-            if let Some(lang) = syntaxes.resolve_ext(ext) {
-                syntax = syntaxes.get(lang);
-            }
-        }
+        // opening for writing doesn't change the file
+        let writable = fs::OpenOptions::new().append(true).open(file.path()).is_ok();
 
-        let tab = Tab::new(syntax, Some(file), text);
+        let syntax = resolve_syntax(syntaxes, &file);
+        let mut tab = Tab::new(syntax, Some(file), text);
         let new_idx = self.inner.len();
+        tab.read_only |= !writable;
+
+        if lossy {
+            tab.set_lossy();
+        }
+
         self.inner.push(tab);
 
         match replace_current {
@@ -399,10 +825,32 @@ impl TabMap {
         }
     }
 
+    // called after the syntax file was reloaded
+    pub fn reload_syntaxes(&mut self, syntaxes: &SyntaxFile) {
+        for tab in self.inner.iter_mut() {
+            let syntax = match tab.file_key.as_ref() {
+                Some(key) => resolve_syntax(syntaxes, key),
+                None => tab.syntax.as_ref().and_then(|s| syntaxes.get(s.name())),
+            };
+
+            tab.set_syntax(syntax);
+        }
+    }
+
+    fn may_close(&self, index: usize) -> bool {
+        let Tab { modified, name, .. } = &self.inner[index];
+        !modified || confirm!("{CLOSE_WARNING}\nReally close {name}? It has unsaved edits!")
+    }
+
     pub fn close(&mut self, index: Option<usize>) {
         let index = index.unwrap_or(self.current);
 
-        if self.inner[index].modified && !confirm!("{}", CLOSE_WARNING) {
+        if self.inner[index].pinned {
+            alert!("This tab is pinned; unpin it before closing it.");
+            return;
+        }
+
+        if !self.may_close(index) {
             return;
         }
 
@@ -420,6 +868,34 @@ impl TabMap {
         self.current().set_lines_redraw();
     }
 
+    // tabs left of `keep` are spared when `to_the_right` is set
+    pub fn close_others(&mut self, mut keep: usize, to_the_right: bool) {
+        let mut current = Some(self.current);
+
+        for i in (0..self.inner.len()).rev() {
+            let spared = i == keep || (to_the_right && i < keep) || self.inner[i].pinned;
+
+            if spared || !self.may_close(i) {
+                continue;
+            }
+
+            self.inner.remove(i);
+
+            if i < keep {
+                keep -= 1;
+            }
+
+            current = match current {
+                Some(c) if c == i => None,
+                Some(c) if c > i => Some(c - 1),
+                other => other,
+            };
+        }
+
+        self.current = current.unwrap_or(keep);
+        self.current().set_lines_redraw();
+    }
+
     pub fn next_tab(&mut self, leftward: bool) {
         let p = self.current + 1;
         let max = self.inner.len().saturating_sub(1);
@@ -433,6 +909,16 @@ impl TabMap {
         self.current().set_lines_redraw();
     }
 
+    // unnamed tabs have no key
+    pub fn switch_to(&mut self, key: Option<&FileKey>) -> bool {
+        match self.inner.iter().position(|t| t.file_key.as_ref() == key) {
+            Some(index) => self.switch(index),
+            None => return false,
+        }
+
+        true
+    }
+
     pub fn switch(&mut self, index: usize) {
         self.current = index;
         self.current().set_lines_redraw();
@@ -442,6 +928,20 @@ impl TabMap {
         self.inner.iter().all(|t| !t.modified)
     }
 
+    // after a rename in the tree
+    pub fn rebase(&mut self, old: &FileKey, new: &FileKey) {
+        for tab in self.inner.iter_mut() {
+            let Some(key) = tab.file_key.as_ref() else {
+                continue;
+            };
+
+            if let Some(key) = key.rebase(old, new) {
+                tab.file_key = Some(key);
+                tab.name = file_name(&tab.file_key);
+            }
+        }
+    }
+
     pub fn is_in_use(&self, parent: &FileKey) -> bool {
         for tab in &self.inner {
             if let Some(key) = tab.key() {
@@ -472,6 +972,12 @@ impl Ord for Cursor {
     }
 }
 
+fn resolve_syntax(syntaxes: &SyntaxFile, file: &FileKey) -> Option<Arc<SyntaxConfig>> {
+    let (_, ext) = file.path().rsplit_once('.')?;
+    let lang = syntaxes.resolve_ext(ext)?;
+    syntaxes.get(lang)
+}
+
 fn file_name(maybe_key: &Option<FileKey>) -> Arc<str> {
     let name = match maybe_key {
         Some(key) => match key.path().rsplit_once('/') {
@@ -483,3 +989,16 @@ fn file_name(maybe_key: &Option<FileKey>) -> Arc<str> {
 
     name.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn final_line_feeds_round_trip() {
+        for text in ["a\nb", "a\nb\n", "a\r\nb", "a\r\nb\r\n", "", "\n"] {
+            let mut tab = Tab::new(None, None, text.into());
+            assert_eq!(tab.save(), text);
+        }
+    }
+}
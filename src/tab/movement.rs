@@ -1,7 +1,40 @@
 use super::*;
 
+fn char_class(c: char) -> u8 {
+    match c {
+        c if c.is_whitespace() => 0,
+        c if c.is_alphanumeric() || c == '_' => 1,
+        _ => 2,
+    }
+}
+
+// skips whitespaces, then one run of similar chars
+fn word_len(chars: impl Iterator<Item = char>) -> usize {
+    let mut chars = chars.peekable();
+    let mut len = 0;
+
+    while chars.next_if(|c| c.is_whitespace()).is_some() {
+        len += 1;
+    }
+
+    if let Some(first) = chars.next() {
+        let class = char_class(first);
+        len += 1;
+
+        while chars.next_if(|c| char_class(*c) == class).is_some() {
+            len += 1;
+        }
+    }
+
+    len
+}
+
 impl Tab {
     pub fn vertical_jump(&mut self, delta: isize, select: bool) {
+        if self.wrap {
+            return self.wrapped_vertical_jump(delta, select);
+        }
+
         self.unselect_if_not(select, None);
 
         for c in 0..self.cursors.len() {
@@ -9,18 +42,21 @@ impl Tab {
             // forth for nothing with cursor indices
             let cursor = &mut self.cursors[c];
             let line = &self.lines[cursor.y];
-            let x = line.cells_until(cursor.x, self.tab_width_m1);
+            let x = cursor.goal_cells(line, self.tab_width_m1);
             let cx_backup = cursor.x as isize;
 
             let y = cursor.y as isize + delta;
             if let Ok(y) = usize::try_from(y) {
                 if y < self.lines.len() {
+                    let y = self.skip_folds(y, delta > 0);
                     self.seek_in_line(c, y, x);
                 }
             }
 
+            let cursor = &mut self.cursors[c];
+            cursor.goal = Some((x, cursor.x, cursor.y));
+
             if select {
-                let cursor = &mut self.cursors[c];
                 cursor.sel_x += cx_backup - cursor.x as isize;
                 cursor.sel_y -= delta;
             }
@@ -29,6 +65,66 @@ impl Tab {
         self.check_cursors();
     }
 
+    // moves by screen rows rather than by lines
+    fn wrapped_vertical_jump(&mut self, delta: isize, select: bool) {
+        self.unselect_if_not(select, None);
+        let mut starts = Vec::new();
+
+        for c in 0..self.cursors.len() {
+            let Cursor { x, y, .. } = self.cursors[c];
+            let line = &self.lines[y];
+            let cells = line.cells_until(x, self.tab_width_m1);
+
+            self.row_starts(y, &mut starts);
+            let row = starts.partition_point(|s| *s <= x) - 1;
+            let row_cells = line.cells_until(starts[row], self.tab_width_m1);
+
+            // the goal is an offset in the row when wrapping
+            let offset = match self.cursors[c].goal {
+                Some((offset, gx, gy)) if (gx, gy) == (x, y) => offset,
+                _ => cells - row_cells,
+            };
+
+            // row of the target, relative to its line
+            let (mut new_y, mut new_row) = (y, row as isize + delta);
+
+            while new_row < 0 && new_y > 0 {
+                new_y = self.skip_folds(new_y - 1, false);
+                self.row_starts(new_y, &mut starts);
+                new_row += starts.len() as isize;
+            }
+
+            self.row_starts(new_y, &mut starts);
+
+            while new_row >= starts.len() as isize && new_y + 1 < self.lines.len() {
+                new_row -= starts.len() as isize;
+                new_y = self.skip_folds(new_y + 1, true);
+                self.row_starts(new_y, &mut starts);
+            }
+
+            let new_row = new_row.clamp(0, starts.len() as isize - 1) as usize;
+            let start = starts[new_row];
+            let start_cells = self.lines[new_y].cells_until(start, self.tab_width_m1);
+            self.seek_in_line(c, new_y, start_cells + offset);
+
+            let cursor = &mut self.cursors[c];
+
+            // stay on the target row
+            if let Some(next_start) = starts.get(new_row + 1) {
+                cursor.x = cursor.x.min(next_start - 1);
+            }
+
+            cursor.goal = Some((offset, cursor.x, cursor.y));
+
+            if select {
+                cursor.sel_x += x as isize - cursor.x as isize;
+                cursor.sel_y += y as isize - new_y as isize;
+            }
+        }
+
+        self.check_cursors();
+    }
+
     fn backoff_cursor_once(&mut self, c: usize, select: bool) {
         let cursor = &mut self.cursors[c];
 
@@ -73,7 +169,7 @@ impl Tab {
         }
     }
 
-    fn hor_jump_cursor(&mut self, c: usize, delta: isize, select: bool) {
+    pub(super) fn hor_jump_cursor(&mut self, c: usize, delta: isize, select: bool) {
         type Sig = (usize, fn(&mut Tab, usize, bool));
 
         let (num_iter, callback): Sig = match delta < 0 {
@@ -101,21 +197,60 @@ impl Tab {
         self.check_cursors();
     }
 
-    fn seek_in_line(&mut self, c: usize, y: usize, mut x: usize) {
+    pub(super) fn word_delta(&self, c: usize, forward: bool) -> isize {
+        let cursor = &self.cursors[c];
+        let line = &self.lines[cursor.y];
+        let split = line.len_until(cursor.x);
+
+        let len = match forward {
+            true => word_len(line.buffer[split..].chars()),
+            false => word_len(line.buffer[..split].chars().rev()),
+        };
+
+        // at the edge of the line: cross it
+        let len = len.max(1) as isize;
+
+        match forward {
+            true => len,
+            false => -len,
+        }
+    }
+
+    pub fn word_jump(&mut self, forward: bool, select: bool) {
+        self.unselect_if_not(select, Some(!forward));
+
+        for c in 0..self.cursors.len() {
+            let delta = self.word_delta(c, forward);
+            self.hor_jump_cursor(c, delta, select);
+        }
+
+        self.check_cursors();
+    }
+
+    // edits make goal columns meaningless
+    pub(super) fn forget_goals(&mut self) {
+        for cursor in self.cursors.iter_mut() {
+            cursor.goal = None;
+        }
+    }
+
+    // `x` is in cells; the cursor lands before the char covering
+    // that cell, so tabs and wide chars don't push it further right
+    fn seek_in_line(&mut self, c: usize, y: usize, x: usize) {
         let cursor = &mut self.cursors[c];
         self.lines[cursor.y].must_draw = true;
         let line = &self.lines[y];
         let mut progress = 0;
+        let mut cells = 0;
 
         for c in line.buffer.chars() {
-            if progress >= x {
-                break;
-            }
+            let width = char_width(c, self.tab_width_m1);
 
-            if c == '\t' {
-                x = x.saturating_sub(self.tab_width_m1);
+            if cells + width > x {
+                break;
             }
 
+            cells += width;
             progress += 1;
         }
 
@@ -125,18 +260,15 @@ impl Tab {
     }
 
     pub fn seek(&mut self, x: u16, y: u16, append: bool) {
-        let Some(y) = self.line_index(y) else {
+        self.block_anchor = None;
+
+        let Some((y, x)) = self.screen_to_line(x, y) else {
             return;
         };
 
-        let mut x = x as usize;
         let c = self.latest_cursor();
         let cursor = &self.cursors[c];
 
-        if cursor.y == y {
-            x += self.h_scroll.saturating_sub(1);
-        }
-
         let same_xy = (cursor.x == x) & (cursor.y == y);
 
         if same_xy && !cursor.selects() {
@@ -160,28 +292,59 @@ impl Tab {
         self.check_cursors();
     }
 
+    pub fn add_cursor_vertical(&mut self, up: bool) {
+        let c = self.latest_cursor();
+        let Cursor { x, y, .. } = self.cursors[c];
+
+        let target_y = match up {
+            true => y.checked_sub(1),
+            false => Some(y + 1).filter(|y| *y < self.lines.len()),
+        };
+
+        let Some(target_y) = target_y else {
+            return;
+        };
+
+        let cells = self.lines[y].cells_until(x, self.tab_width_m1);
+        let next_id = self.cursors.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        let new = self.cursors.len();
+
+        let mut cursor = Cursor::new(next_id);
+        cursor.y = target_y;
+        self.cursors.push(cursor);
+        self.seek_in_line(new, target_y, cells);
+
+        let new_x = self.cursors[new].x;
+        let same_pos = |c: &&mut Cursor| (c.x, c.y) == (new_x, target_y);
+
+        // an existing cursor there becomes the latest one instead
+        if let Some(existing) = self.cursors[..new].iter_mut().find(same_pos) {
+            existing.id = next_id;
+            self.cursors.pop();
+        }
+
+        self.check_cursors();
+    }
+
     pub fn latest_cursor(&mut self) -> usize {
         let iter = self.cursors.iter().enumerate();
         iter.max_by_key(|(_, c)| c.id).unwrap().0
     }
 
-    pub fn drag_to(&mut self, x: u16, y: u16) {
-        let Some(y) = self.line_index(y) else {
+    // the latest cursor moves, its selection anchor stays:
+    // used by drags and by shift-clicks
+    pub fn extend_to(&mut self, x: u16, y: u16) {
+        let Some((y, x)) = self.screen_to_line(x, y) else {
             return;
         };
 
-        let mut x = x as usize;
         let c = self.latest_cursor();
         let cursor = &mut self.cursors[c];
 
-        if cursor.y == y {
-            x += self.h_scroll.saturating_sub(1);
-        }
-
         cursor.swap_sel_direction();
         let backup = *cursor;
 
-        self.seek_in_line(c, y as usize, x as usize);
+        self.seek_in_line(c, y, x);
 
         let cursor = &mut self.cursors[c];
         cursor.sel_x = (backup.x as isize) - (cursor.x as isize);
@@ -191,6 +354,90 @@ impl Tab {
         self.set_lines_redraw();
     }
 
+    // one selection per line, between the columns
+    // of the drag origin and of (x, y)
+    pub fn block_drag_to(&mut self, x: u16, y: u16) {
+        let Some((y, cells)) = self.screen_to_line(x, y) else {
+            return;
+        };
+
+        // the latest cursor was placed by the click starting the drag
+        if self.block_anchor.is_none() {
+            let c = self.latest_cursor();
+            let Cursor { x, y, .. } = self.cursors[c];
+            self.block_anchor = Some((y, self.lines[y].cells_until(x, self.tab_width_m1)));
+        }
+
+        let (anchor_y, anchor_cells) = self.block_anchor.unwrap();
+
+        for cursor in &self.cursors {
+            self.lines[cursor.y].must_draw = true;
+        }
+
+        self.cursors.clear();
+
+        let mut rows: Vec<_> = match anchor_y <= y {
+            true => (anchor_y..=y).collect(),
+            // the dragged line holds the latest cursor
+            false => (y..=anchor_y).rev().collect(),
+        };
+
+        rows.retain(|l| !self.is_folded(*l));
+
+        for line_y in rows {
+            let id = self.cursors.len();
+            self.cursors.push(Cursor::new(id));
+
+            // shorter lines get a cursor at their end
+            self.seek_in_line(id, line_y, anchor_cells);
+            let origin_x = self.cursors[id].x;
+            self.seek_in_line(id, line_y, cells);
+
+            let cursor = &mut self.cursors[id];
+            cursor.sel_x = origin_x as isize - cursor.x as isize;
+        }
+
+        self.check_cursors();
+        self.set_lines_redraw();
+    }
+
+    // from the start of `from_y` to the end of `to_y`, or backwards
+    pub fn select_line_range(&mut self, from_y: usize, to_y: usize) {
+        for cursor in &self.cursors {
+            self.lines[cursor.y].must_draw = true;
+        }
+
+        let (origin_x, x) = match from_y <= to_y {
+            true => (0, self.lines[to_y].len_chars()),
+            false => (self.lines[from_y].len_chars(), 0),
+        };
+
+        let mut cursor = Cursor::new(0);
+        (cursor.x, cursor.y) = (x, to_y);
+        cursor.sel_x = origin_x as isize - x as isize;
+        cursor.sel_y = from_y as isize - to_y as isize;
+
+        self.cursors = vec![cursor];
+        self.set_lines_redraw();
+    }
+
+    // extending keeps the line where the selection started
+    pub fn select_line_at_row(&mut self, screen_y: u16, extend: bool) {
+        let Some((y, _)) = self.screen_line(screen_y) else {
+            return;
+        };
+
+        let c = self.latest_cursor();
+        let cursor = &self.cursors[c];
+
+        let from_y = match extend {
+            true => cursor.y.saturating_add_signed(cursor.sel_y),
+            false => y,
+        };
+
+        self.select_line_range(from_y, y);
+    }
+
     fn unselect_if_not(&mut self, select: bool, jump_dir: Option<bool>) {
         if select {
             return;
@@ -308,19 +555,46 @@ impl Tab {
         }
     }
 
-    fn find(&self, text: &str, mut start_x: usize, start_y: usize) -> Option<(usize, usize)> {
+    // with `wrap`, continues from the top of the file up to the start position
+    pub(super) fn find(
+        &self,
+        text: &str,
+        start_x: usize,
+        start_y: usize,
+        wrap: bool,
+    ) -> Option<(usize, usize)> {
         let lines = self.lines.len();
+        let mut from_x = start_x;
 
         for y in start_y..lines {
             let len = self.lines[y].len_chars();
 
-            for x in start_x..=len {
+            for x in from_x..=len {
                 if self.matches(text, x, y) {
                     return Some((x, y));
                 }
             }
 
-            start_x = 0;
+            from_x = 0;
+        }
+
+        if !wrap {
+            return None;
+        }
+
+        for y in 0..=start_y.min(lines - 1) {
+            let len = self.lines[y].len_chars();
+
+            let end_x = match y == start_y {
+                true => start_x.min(len + 1),
+                false => len + 1,
+            };
+
+            for x in 0..end_x {
+                if self.matches(text, x, y) {
+                    return Some((x, y));
+                }
+            }
         }
 
         None
@@ -343,12 +617,12 @@ impl Tab {
         }
     }
 
-    pub fn locate(&mut self, text: &str) {
+    pub fn locate(&mut self, text: &str) -> usize {
         let num_chars = text.chars().count() as isize;
         let mut cursor = Cursor::new(0);
         let mut c = 0;
 
-        while let Some((x, y)) = self.find(&text, cursor.x, cursor.y) {
+        while let Some((x, y)) = self.find(&text, cursor.x, cursor.y, false) {
             if c == 0 {
                 self.cursors.clear();
             }
@@ -368,6 +642,33 @@ impl Tab {
 
         self.check_cursors();
         self.h_scroll = 0;
+        c
+    }
+
+    // focuses the selection starting at (x, y),
+    // or moves to (x, y) if there is none
+    pub fn goto(&mut self, x: usize, y: usize) {
+        let starts_at = |c: &Cursor| {
+            let start_x = c.x.checked_add_signed(c.sel_x);
+            let start_y = c.y.checked_add_signed(c.sel_y);
+            (start_x, start_y) == (Some(x), Some(y))
+        };
+
+        if let Some(c) = self.cursors.iter().position(starts_at) {
+            self.swap_latest_cursor(c);
+            return;
+        }
+
+        let y = y.min(self.lines.len() - 1);
+        let x = x.min(self.lines[y].len_chars());
+
+        for cursor in &self.cursors {
+            self.lines[cursor.y].must_draw = true;
+        }
+
+        let mut cursor = Cursor::new(0);
+        (cursor.x, cursor.y) = (x, y);
+        self.cursors = vec![cursor];
     }
 
     pub(super) fn extract_selection<T: AppendStr>(&self, c: usize, dst: &mut T) {
@@ -400,6 +701,31 @@ impl Tab {
         }
     }
 
+    // lines touched by each cursor, sorted
+    pub(super) fn touched_lines(&self) -> Vec<usize> {
+        let mut lines = Vec::new();
+
+        for cursor in &self.cursors {
+            let mut a = *cursor;
+            let mut b = a;
+
+            a.sel_jump(true);
+            b.sel_jump(false);
+
+            // a selection stopping at the start of a line doesn't touch it
+            let last_y = match (b.x, b.y > a.y) {
+                (0, true) => b.y - 1,
+                _ => b.y,
+            };
+
+            lines.extend(a.y..=last_y);
+        }
+
+        lines.sort();
+        lines.dedup();
+        lines
+    }
+
     fn find_next_occurence(&mut self, c: usize) {
         let mut text = String::new();
         self.extract_selection(c, &mut text);
@@ -407,7 +733,18 @@ impl Tab {
         let chars = text.chars().count();
         cursor.sel_jump(false);
 
-        if let Some((x, y)) = self.find(&text, cursor.x, cursor.y) {
+        if let Some((x, y)) = self.find(&text, cursor.x, cursor.y, true) {
+            let selected = |c: &Cursor| {
+                let mut start = *c;
+                start.sel_jump(true);
+                (start.x, start.y) == (x, y)
+            };
+
+            // every occurrence is already selected
+            if self.cursors.iter().any(selected) {
+                return;
+            }
+
             let id = self.cursors.len();
             cursor.sel_jump(true);
 
@@ -417,6 +754,7 @@ impl Tab {
                 sel_x: 0,
                 sel_y: 0,
                 id,
+                goal: None,
             };
 
             self.cursors.push(new_cursor);
@@ -432,9 +770,12 @@ impl Tab {
             let cursor = &self.cursors[c];
             let line = &self.lines[cursor.y];
 
-            let target = match to_start {
-                true => 0,
-                false => line.len_chars() as isize,
+            // first non-whitespace char, then column 0
+            let indent = line.indent_chars();
+            let target = match (to_start, cursor.x == indent) {
+                (true, true) => 0,
+                (true, false) => indent as isize,
+                (false, _) => line.len_chars() as isize,
             };
 
             let delta = target - (cursor.x as isize);
@@ -444,6 +785,161 @@ impl Tab {
         self.check_cursors();
     }
 
+    pub fn split_selection_into_lines(&mut self) {
+        let mut next_id = self.cursors.iter().map(|c| c.id).max().unwrap_or(0);
+        let mut split = Vec::new();
+
+        for cursor in &self.cursors {
+            if cursor.sel_y == 0 {
+                continue;
+            }
+
+            let mut a = *cursor;
+            let mut b = a;
+
+            a.sel_jump(true);
+            b.sel_jump(false);
+
+            // the tail line isn't covered if the selection stops at its start
+            let last_y = match b.x {
+                0 => b.y - 1,
+                _ => b.y,
+            };
+
+            for y in a.y..=b.y {
+                self.lines[y].must_draw = true;
+            }
+
+            for y in a.y..=last_y {
+                next_id += 1;
+                let mut new_cursor = Cursor::new(next_id);
+                new_cursor.x = b.x.min(self.lines[y].len_chars());
+                new_cursor.y = y;
+                split.push(new_cursor);
+            }
+        }
+
+        self.cursors.retain(|c| c.sel_y == 0);
+        self.cursors.extend(split);
+        self.check_cursors();
+    }
+
+    pub fn cursors_at_line_edges(&mut self, at_end: bool) {
+        let mut next_id = self.cursors.iter().map(|c| c.id).max().unwrap_or(0);
+        let mut edges = Vec::new();
+
+        for cursor in &self.cursors {
+            let mut a = *cursor;
+            let mut b = a;
+
+            a.sel_jump(true);
+            b.sel_jump(false);
+
+            // the tail line isn't covered if the selection stops at its start
+            let last_y = match (b.x, b.y > a.y) {
+                (0, true) => b.y - 1,
+                _ => b.y,
+            };
+
+            for y in a.y..=b.y {
+                self.lines[y].must_draw = true;
+            }
+
+            for y in a.y..=last_y {
+                next_id += 1;
+                let mut new_cursor = Cursor::new(next_id);
+                new_cursor.y = y;
+
+                if at_end {
+                    new_cursor.x = self.lines[y].len_chars();
+                }
+
+                edges.push(new_cursor);
+            }
+        }
+
+        self.cursors = edges;
+        self.check_cursors();
+    }
+
+    pub fn move_lines(&mut self, up: bool) {
+        let mut blocks: Vec<(usize, usize)> = Vec::new();
+
+        // contiguous blocks, so that cursors don't double-move
+        for y in self.touched_lines() {
+            match blocks.last_mut() {
+                Some((_, end)) if *end + 1 == y => *end = y,
+                _ => blocks.push((y, y)),
+            }
+        }
+
+        let last_y = self.lines.len() - 1;
+        let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+            return;
+        };
+
+        if (up && first.0 == 0) || (!up && last.1 == last_y) {
+            return;
+        }
+
+        let first_y = match up {
+            true => first.0 - 1,
+            false => first.0,
+        };
+
+        self.prepare_insertion();
+
+        if !up {
+            blocks.reverse();
+        }
+
+        for (start, end) in blocks {
+            let map: &dyn Fn(usize) -> usize = match up {
+                true => &|y| match y == start - 1 {
+                    true => end,
+                    false => y - (start..=end).contains(&y) as usize,
+                },
+                false => &|y| match y == end + 1 {
+                    true => start,
+                    false => y + (start..=end).contains(&y) as usize,
+                },
+            };
+
+            // the line next to the block goes to its other side
+            let (from, to) = match up {
+                true => (start - 1, end),
+                false => (end + 1, start),
+            };
+
+            let line = self.remove_line(from);
+            self.insert_line(to, line);
+
+            for cursor in self.cursors.iter_mut() {
+                let origin_y = (cursor.y as isize + cursor.sel_y) as usize;
+                cursor.y = map(cursor.y);
+                cursor.sel_y = map(origin_y) as isize - cursor.y as isize;
+            }
+
+            for bookmark in self.bookmarks.iter_mut() {
+                *bookmark = map(*bookmark);
+            }
+
+            for (header, last) in self.folds.iter_mut() {
+                *header = map(*header);
+                *last = map(*last);
+            }
+        }
+
+        self.bookmarks.sort_unstable();
+        self.folds.sort_unstable();
+        self.folds_dirty = true;
+
+        // moved lines are re-highlighted if their context changed
+        self.set_lines_redraw_from(first_y);
+        self.check_cursors();
+        self.modified = true;
+    }
+
     pub fn select_all(&mut self) {
         let Some(last) = self.lines.last() else {
             return;
@@ -478,3 +974,82 @@ impl AppendStr for usize {
         *self += text.chars().count();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // char index of a cursor aiming at `cells` on the first line
+    fn landing(text: &str, tab_width: usize, cells: usize) -> usize {
+        let mut tab = Tab::new(None, None, text.into());
+        tab.tab_width_m1 = tab_width - 1;
+        tab.seek_in_line(0, 0, cells);
+        tab.cursors[0].x
+    }
+
+    #[test]
+    fn seek_lands_before_the_covering_char() {
+        for tab_width in [2, 4, 8] {
+            // the tab covers cells 0 to `tab_width - 1`
+            assert_eq!(landing("\tx", tab_width, 0), 0);
+            assert_eq!(landing("\tx", tab_width, tab_width - 1), 0);
+            assert_eq!(landing("\tx", tab_width, tab_width), 1);
+            assert_eq!(landing("\tx", tab_width, tab_width + 1), 2);
+
+            // the tab starts on cell 2
+            assert_eq!(landing("  \tx", tab_width, 1), 1);
+            assert_eq!(landing("  \tx", tab_width, 2), 2);
+            assert_eq!(landing("  \tx", tab_width, tab_width + 1), 2);
+            assert_eq!(landing("  \tx", tab_width, tab_width + 2), 3);
+
+            assert_eq!(landing(" \t \t", tab_width, 100), 4);
+        }
+
+        // wide chars take two cells
+        assert_eq!(landing("日本x", 4, 1), 0);
+        assert_eq!(landing("日本x", 4, 2), 1);
+        assert_eq!(landing("日本x", 4, 4), 2);
+        assert_eq!(landing("\t日x", 2, 3), 1);
+    }
+
+    #[test]
+    fn vertical_jumps_keep_the_visual_column() {
+        for tab_width in [2, 4, 8] {
+            let spaces = " ".repeat(tab_width);
+            let text = format!("\tab\n{spaces}cd\n \tef");
+            let mut tab = Tab::new(None, None, text);
+            tab.tab_width_m1 = tab_width - 1;
+
+            // right after the tab, then right after the spaces
+            tab.goto(1, 0);
+            tab.vertical_jump(1, false);
+            assert_eq!((tab.cursors[0].x, tab.cursors[0].y), (tab_width, 1));
+
+            // this tab covers the column, the cursor lands before it
+            tab.vertical_jump(1, false);
+            assert_eq!((tab.cursors[0].x, tab.cursors[0].y), (1, 2));
+
+            // inside the tab, the cursor lands before it
+            tab.goto(1, 1);
+            tab.vertical_jump(-1, false);
+            assert_eq!((tab.cursors[0].x, tab.cursors[0].y), (0, 0));
+        }
+    }
+
+    #[test]
+    fn moved_lines_carry_bookmarks_and_folds() {
+        let mut tab = Tab::new(None, None, "a\nc\n  d\ne".into());
+        tab.bookmarks = vec![0, 3];
+        tab.toggle_fold(1);
+
+        // the folded block goes above the first line
+        tab.cursors[0] = Cursor { x: 3, y: 2, sel_x: -3, sel_y: -1, ..tab.cursors[0] };
+        tab.move_lines(true);
+        assert_eq!(tab.save().to_string(), "c\n  d\na\ne");
+        assert_eq!(tab.bookmarks, [2, 3]);
+        assert_eq!(tab.folds, [(0, 1)]);
+
+        tab.undo();
+        assert_eq!(tab.save().to_string(), "a\nc\n  d\ne");
+    }
+}
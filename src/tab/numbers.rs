@@ -0,0 +1,121 @@
+use super::*;
+
+// integer found around a cursor
+struct Number {
+    // chars of the line to rewrite: digits and sign, not the 0x prefix
+    start: usize,
+    end: usize,
+    value: i64,
+    hex: bool,
+    upper: bool,
+    // leading zeros are kept up to this many digits
+    width: usize,
+}
+
+impl Number {
+    fn find(chars: &[char], x: usize) -> Option<Self> {
+        let around = |f: fn(&char) -> bool| {
+            let before = chars[..x].iter().rev().take_while(|c| f(c)).count();
+            let after = chars[x..].iter().take_while(|c| f(c)).count();
+            (x - before, x + after)
+        };
+
+        let (start, end) = around(char::is_ascii_alphanumeric);
+
+        if let ['0', 'x' | 'X', digits @ ..] = &chars[start..end] {
+            if !digits.is_empty() && digits.iter().all(char::is_ascii_hexdigit) {
+                let text: String = digits.iter().collect();
+
+                return Some(Self {
+                    start: start + 2,
+                    end,
+                    value: i64::from_str_radix(&text, 16).ok()?,
+                    hex: true,
+                    upper: text.chars().any(|c| c.is_ascii_uppercase()),
+                    width: text.len(),
+                });
+            }
+        }
+
+        let (mut start, end) = around(char::is_ascii_digit);
+
+        if start == end {
+            return None;
+        }
+
+        let text: String = chars[start..end].iter().collect();
+        let mut value: i64 = text.parse().ok()?;
+
+        let width = match text.starts_with('0') {
+            true => text.len(),
+            false => 0,
+        };
+
+        // not a subtraction
+        let operand = start > 1 && chars[start - 2].is_ascii_alphanumeric();
+
+        if start > 0 && chars[start - 1] == '-' && !operand {
+            start -= 1;
+            value = -value;
+        }
+
+        Some(Self { start, end, value, hex: false, upper: false, width })
+    }
+
+    fn format(&self, value: i64) -> Option<String> {
+        let width = self.width;
+
+        let text = match (self.hex, self.upper) {
+            (true, _) if value < 0 => return None,
+            (true, true) => format!("{value:0width$X}"),
+            (true, false) => format!("{value:0width$x}"),
+            _ if value < 0 => format!("-{:0width$}", value.unsigned_abs()),
+            _ => format!("{value:0width$}"),
+        };
+
+        Some(text)
+    }
+}
+
+impl Tab {
+    pub fn adjust_number(&mut self, delta: i64) {
+        let mut edits = Vec::new();
+
+        for cursor in self.cursors.iter() {
+            let chars: Vec<char> = self.lines[cursor.y].buffer.chars().collect();
+
+            let Some(number) = Number::find(&chars, cursor.x) else {
+                continue;
+            };
+
+            let new_value = number.value.checked_add(delta);
+
+            if let Some(text) = new_value.and_then(|v| number.format(v)) {
+                edits.push((cursor.y, number.start, number.end, text));
+            }
+        }
+
+        // cursors sharing a number change it once
+        edits.dedup();
+
+        if edits.is_empty() {
+            return;
+        }
+
+        self.prepare_insertion();
+
+        // right to left, so that positions stay valid
+        for (y, start, end, text) in edits.into_iter().rev() {
+            let line = self.line_mut(y);
+            let offsets = line.len_until(start)..line.len_until(end);
+            line.buffer.replace_range(offsets, &text);
+            line.set_dirty();
+
+            // cursors after the number follow its end
+            let new_end = start + text.chars().count();
+            self.shift_cursors_in_line(y, end.min(new_end), new_end as isize - end as isize);
+        }
+
+        self.modified = true;
+    }
+}
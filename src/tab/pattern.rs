@@ -0,0 +1,107 @@
+use ::regex::{Captures, Regex};
+use super::*;
+
+pub const REGEX_PREFIX: &str = "re:";
+
+// Ok(None) if `needle` isn't a regex
+pub(super) fn parse_regex(needle: &str) -> Result<Option<Regex>, String> {
+    let Some(pattern) = needle.strip_prefix(REGEX_PREFIX) else {
+        return Ok(None);
+    };
+
+    Regex::new(pattern).map(Some).map_err(|e| e.to_string())
+}
+
+impl Tab {
+    // (x, y, length in chars) of each match; matches can't span lines
+    pub(super) fn regex_matches(&self, re: &Regex) -> Vec<(usize, usize, usize)> {
+        self.regex_captures(re, |_| ())
+    }
+
+    // captures are seen in the context of their whole line
+    fn regex_captures<F: FnMut(&Captures)>(
+        &self,
+        re: &Regex,
+        mut each: F,
+    ) -> Vec<(usize, usize, usize)> {
+        let mut found = Vec::new();
+
+        for (y, line) in self.lines.iter().enumerate() {
+            for caps in re.captures_iter(&line.buffer) {
+                let m = caps.get_match();
+
+                if m.is_empty() {
+                    continue;
+                }
+
+                let x = line.buffer[..m.start()].chars().count();
+                let len = m.as_str().chars().count();
+                found.push((x, y, len));
+                each(&caps);
+            }
+        }
+
+        found
+    }
+
+    fn locate_regex(&mut self, found: &[(usize, usize, usize)]) {
+        self.cursors.clear();
+
+        for (c, (x, y, len)) in found.iter().enumerate() {
+            let mut cursor = Cursor::new(c);
+            cursor.x = *x;
+            cursor.y = *y;
+            self.cursors.push(cursor);
+            self.hor_jump_cursor(c, *len as isize, true);
+        }
+
+        self.check_cursors();
+        self.h_scroll = 0;
+    }
+
+    pub(super) fn replace_regex(&mut self, re: &Regex, replacement: &str) {
+        let mut texts = Vec::new();
+
+        // expands capture groups, like `$1`
+        let found = self.regex_captures(re, |caps| {
+            let mut text = String::new();
+            caps.expand(replacement, &mut text);
+            texts.push(text);
+        });
+
+        if found.is_empty() {
+            return alert!("No match in current tab.");
+        }
+
+        self.locate_regex(&found);
+        self.prepare_insertion();
+        self.erase_selection();
+
+        for (c, text) in texts.iter().enumerate() {
+            self.insert_text_cursor(c, text);
+        }
+
+        self.modified = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replaced(text: &str, needle: &str, replacement: &str) -> String {
+        let mut tab = Tab::new(None, None, text.into());
+        let re = parse_regex(needle).unwrap().unwrap();
+        tab.replace_regex(&re, replacement);
+        tab.save().to_string()
+    }
+
+    #[test]
+    fn replacements_see_the_whole_line() {
+        assert_eq!(replaced("xfoo foo\nfoox", r"re:\Bfoo", "bar"), "xbar foo\nfoox");
+        assert_eq!(replaced("aa\nab", "re:^a", "b"), "ba\nbb");
+        let swapped = replaced("key = value, a = b", r"re:(\w+) = (\w+)", "$2: $1");
+        assert_eq!(swapped, "value: key, b: a");
+        assert_eq!(replaced("ab cd", r"re:\b(\w)", "<$1>"), "<a>b <c>d");
+    }
+}
@@ -1,47 +1,159 @@
 use super::*;
 
+// overrides the mode of `len` bytes at `offset`, across parts
+fn override_part(parts: &mut Vec<TextPart>, offset: usize, len: usize, mode: &'static str) {
+    let end = offset + len;
+    let old_parts = parts.clone();
+    let mut inserted = false;
+    let mut start = 0;
+
+    parts.clear();
+
+    for (part_mode, part_len) in old_parts {
+        let part_end = start + part_len;
+
+        let head = part_end.min(offset).saturating_sub(start);
+        if head > 0 {
+            parts.push((part_mode, head));
+        }
+
+        if !inserted && part_end > offset {
+            parts.push((mode, len));
+            inserted = true;
+        }
+
+        let tail_start = start.max(end);
+        if part_end > tail_start {
+            parts.push((part_mode, part_end - tail_start));
+        }
+
+        start = part_end;
+    }
+}
+
+// lines highlighted past the bottom of the screen,
+// so that short scrolls find them ready
+const HIGHLIGHT_MARGIN: usize = 64;
+
+// one cell is kept for a cursor at the end of lines
+fn wrap_width(code_width: usize) -> usize {
+    code_width.saturating_sub(1).max(1)
+}
+
 impl Tab {
     pub fn highlight(&mut self) {
-        let Some(syntax) = self.syntax.as_ref() else {
-            return;
-        };
+        self.highlight_until(self.lines.len());
+    }
+
+    // lines below the window keep their pending state
+    // and get highlighted once they're scrolled into view
+    pub fn highlight_visible(&mut self, top: usize, height: usize) {
+        self.check_folds();
 
-        let mut ctx = None;
+        // folded lines take no row, so the last
+        // shown line can be far below `top + height`
+        let mut end = top;
+        let mut shown = 0;
 
-        for line in self.lines.iter_mut() {
-            if take(&mut line.must_highlight) {
-                line.eol_ctx = syntax.highlight(ctx, &mut line.ranges, &line.buffer);
+        while shown < height && end < self.lines.len() {
+            if !self.is_folded(end) {
+                shown += 1;
             }
 
-            ctx = line.eol_ctx;
+            end += 1;
         }
+
+        self.highlight_until(end + HIGHLIGHT_MARGIN);
     }
 
-    pub(super) fn check_line_highlighting(&mut self, index: usize) {
-        let Some(syntax) = self.syntax.as_ref() else {
-            return;
-        };
+    // only lines which changed, or whose incoming context
+    // changed, are highlighted again
+    fn highlight_until(&mut self, end: usize) {
+        if let Some(syntax) = self.syntax.as_ref() {
+            let colors = bracket_colors();
+            let mut ctx = None;
+            let mut depth = 0;
 
-        let mut ctx = match index.checked_sub(1) {
-            Some(j) => self.lines[j].eol_ctx,
-            None => None,
-        };
+            for line in self.lines.iter_mut().take(end) {
+                let changed = take(&mut line.must_highlight);
 
-        let line = &mut self.lines[index];
-        ctx = syntax.highlight(ctx, &mut line.ranges, &line.buffer);
+                if changed || line.bol_ctx != ctx || line.bol_depth != depth {
+                    line.bol_ctx = ctx;
+                    line.bol_depth = depth;
+                    line.eol_ctx = syntax.highlight(ctx, &mut line.ranges, &line.buffer);
+                    line.eol_depth = color_brackets(&mut line.ranges, &line.buffer, depth, colors);
+                    line.must_draw = true;
+                }
+
+                ctx = line.eol_ctx;
+                depth = line.eol_depth;
+            }
+        }
+
+        // the matching bracket is only looked for around the window
+        let first = self.v_scroll.saturating_sub(HIGHLIGHT_MARGIN);
+        self.update_bracket_match(first..end);
+    }
 
-        if line.eol_ctx == ctx {
+    // first char of each row taken by a line when wrapped
+    pub(super) fn row_starts(&self, y: usize, dst: &mut Vec<usize>) {
+        self.lines[y].wrap_starts(self.wrap_width, self.tab_width_m1, dst);
+    }
+
+    // maps screen rows to parts of lines, when wrapping or folding
+    pub fn layout_rows(&mut self, width: usize, height: usize) {
+        self.check_folds();
+
+        if !self.uses_rows() {
             return;
         }
 
-        // alert!("flushing later lines");
-        self.set_lines_dirty(index);
+        self.wrap_width = wrap_width(width);
+        let old_rows = take(&mut self.rows);
+        let mut starts = vec![0];
+
+        for y in self.v_scroll..self.lines.len() {
+            if self.rows.len() >= height {
+                break;
+            }
+
+            if self.is_folded(y) {
+                continue;
+            }
+
+            let line = &mut self.lines[y];
+            let must_draw = take(&mut line.must_draw);
+
+            if self.wrap {
+                line.wrap_starts(self.wrap_width, self.tab_width_m1, &mut starts);
+            }
+
+            for (k, start) in starts.iter().copied().enumerate() {
+                let end = starts.get(k + 1).copied();
+                self.rows.push((Row { line: y, start, end }, must_draw));
+            }
+        }
+
+        self.rows.truncate(height);
+
+        // rows showing something else than before
+        for (i, (row, must_draw)) in self.rows.iter_mut().enumerate() {
+            if old_rows.get(i).map(|(old, _)| old) != Some(row) {
+                *must_draw = true;
+            }
+        }
     }
 
-    pub fn prepare_draw(&mut self, y: u16) -> Option<(usize, bool)> {
+    // (line index, must draw, chars of the line to show)
+    pub fn prepare_draw(&mut self, y: u16) -> Option<(usize, bool, Segment)> {
+        if self.uses_rows() {
+            let (row, must_draw) = self.rows.get_mut(y as usize)?;
+            return Some((row.line, take(must_draw), (row.start, row.end)));
+        }
+
         let i = self.line_index(y)?;
         let line = self.lines.get_mut(i)?;
-        Some((i, take(&mut line.must_draw)))
+        Some((i, take(&mut line.must_draw), (0, None)))
     }
 
     pub fn line_data<'a>(
@@ -51,8 +163,8 @@ impl Tab {
         sel_buf: &mut Vec<Selection>,
         cursors: &mut Vec<usize>,
     ) -> DirtyLine<'a> {
-        let latest = self.latest_cursor();
-        let mut horizontal_scroll = 0;
+        // all lines share the same offset
+        let horizontal_scroll = self.h_scroll;
         let tab_width_m1 = self.tab_width_m1;
         let line = &mut self.lines[index];
         let text = &line.buffer;
@@ -72,12 +184,29 @@ impl Tab {
             part_buf.push(("wspace", missing));
         }
 
-        for (c, cursor) in self.cursors.iter().enumerate() {
+        let first_hit = self.found.partition_point(|(_, y, _)| *y < index);
+
+        for (x, _, len) in self.found[first_hit..].iter().take_while(|(_, y, _)| *y == index) {
+            let offset = line.len_until(*x);
+            let len = line.len_until(x + len) - offset;
+            override_part(part_buf, offset, len, "found");
+        }
+
+        for (x, y) in self.bracket_match.into_iter().flatten() {
+            if y == index {
+                let offset = line.len_until(x);
+                let len = text[offset..].chars().next().map_or(0, char::len_utf8);
+                override_part(part_buf, offset, len, "match");
+            }
+        }
+
+        for cursor in self.cursors.iter() {
+            // the origin is above: the cursor's line is the bottom one
             let forward_sel = cursor.sel_y < 0;
 
             if cursor.covers(index) {
                 sel_buf.clear();
-                let len = line.len_chars();
+                let len = line.len_chars() + eol_selection() as usize;
                 sel_buf.push(Selection::new(0, len));
                 return DirtyLine { horizontal_scroll, tab_width_m1, text };
             }
@@ -85,10 +214,6 @@ impl Tab {
             if cursor.y == index {
                 cursors.push(cursor.x);
 
-                if c == latest {
-                    horizontal_scroll = self.h_scroll;
-                }
-
                 if cursor.sel_y != 0 {
                     sel_buf.push(line.half_select(forward_sel, cursor.x));
                 } else if cursor.sel_x < 0 {
@@ -126,7 +251,39 @@ impl Tab {
         self.set_lines_redraw();
     }
 
+    // lines are never scrolled past their end; wrapped ones can't be
+    pub fn h_scroll_by(&mut self, delta: isize) {
+        if self.wrap {
+            return;
+        }
+
+        let tab_width_m1 = self.tab_width_m1;
+        let cells = |line: &Line| line.cells_until(line.len_chars(), tab_width_m1);
+        let widest = self.lines.iter().map(cells).max().unwrap_or(0);
+
+        self.h_scroll = self.h_scroll.saturating_add_signed(delta).min(widest);
+        self.set_lines_redraw();
+    }
+
+    // drags reaching the first or last row scroll by one line
+    pub fn scroll_at_edge(&mut self, screen_y: u16, height: u16) {
+        let delta = match screen_y {
+            0 => -1,
+            y if y + 1 >= height => 1,
+            _ => return,
+        };
+
+        self.scroll(delta);
+        self.check_overscroll();
+    }
+
     pub fn ensure_cursor_visible(&mut self, width: usize, height: usize) {
+        self.unfold_cursor();
+
+        if self.wrap {
+            return self.ensure_wrapped_cursor_visible(width, height);
+        }
+
         let c = self.latest_cursor();
         let cursor = &self.cursors[c];
 
@@ -137,22 +294,154 @@ impl Tab {
 
         let max_y = self.v_scroll + height;
 
-        let invisible_x = cursor.x < self.h_scroll || max_x <= cursor.x;
+        // only applies to latest cursor
+        let line = &self.lines[cursor.y];
+        let x = line.cells_until(cursor.x, self.tab_width_m1);
+
+        let h_scroll = if x < width.saturating_sub(1) {
+            // no need to scroll this line
+            0
+        } else if x < self.h_scroll || max_x <= x {
+            x.saturating_sub(width / 2)
+        } else {
+            self.h_scroll
+        };
+
+        let invisible_x = h_scroll != self.h_scroll;
         let invisible_y = cursor.y < self.v_scroll || max_y <= cursor.y;
 
-        if invisible_x {
-            // only applies to latest cursor
-            let line = &self.lines[cursor.y];
-            let x = line.cells_until(cursor.x, self.tab_width_m1);
-            self.h_scroll = x.saturating_sub(width / 2);
-        }
+        self.h_scroll = h_scroll;
 
         if invisible_y {
-            self.v_scroll = cursor.y.saturating_sub(height / 2);
+            self.center_on_cursor(height);
         }
 
         if invisible_x | invisible_y {
             self.set_lines_redraw();
         }
     }
+
+    // the latest cursor's line goes to the middle of the code area
+    pub fn center_on_cursor(&mut self, height: usize) {
+        let c = self.latest_cursor();
+        self.v_scroll = self.cursors[c].y.saturating_sub(height / 2);
+        self.set_lines_redraw();
+    }
+
+    fn ensure_wrapped_cursor_visible(&mut self, width: usize, height: usize) {
+        let c = self.latest_cursor();
+        let Cursor { x, y, .. } = self.cursors[c];
+        let old_v_scroll = self.v_scroll;
+        let mut starts = Vec::new();
+
+        self.wrap_width = wrap_width(width);
+        self.h_scroll = 0;
+
+        // lines take at least one row each
+        self.v_scroll = self.v_scroll.clamp(y.saturating_sub(height), y);
+
+        let mut counts = Vec::new();
+        for i in self.v_scroll..y {
+            self.row_starts(i, &mut starts);
+
+            match self.is_folded(i) {
+                true => counts.push(0),
+                false => counts.push(starts.len()),
+            }
+        }
+
+        self.row_starts(y, &mut starts);
+        let cursor_row = starts.partition_point(|s| *s <= x);
+        let mut rows = counts.iter().sum::<usize>() + cursor_row;
+
+        for count in counts {
+            if rows <= height {
+                break;
+            }
+
+            rows -= count;
+            self.v_scroll += 1;
+        }
+
+        if self.v_scroll != old_v_scroll {
+            self.set_lines_redraw();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // selections drawn on each line
+    fn selections(tab: &mut Tab) -> Vec<Vec<Selection>> {
+        let mut all = Vec::new();
+
+        for y in 0..tab.lines.len() {
+            let (mut parts, mut sels, mut cursors) = (Vec::new(), Vec::new(), Vec::new());
+            tab.line_data(y, &mut parts, &mut sels, &mut cursors);
+            all.push(sels);
+        }
+
+        all
+    }
+
+    #[test]
+    fn selection_direction_is_not_drawn() {
+        let text = "first\nsecond\nthird\nfourth";
+        let mut down = Tab::new(None, None, text.into());
+        let mut up = Tab::new(None, None, text.into());
+
+        // from (2, 0) to (3, 3), then the other way around
+        down.cursors[0] = Cursor { x: 3, y: 3, sel_x: -1, sel_y: -3, ..down.cursors[0] };
+        up.cursors[0] = Cursor { x: 2, y: 0, sel_x: 1, sel_y: 3, ..up.cursors[0] };
+
+        let drawn = selections(&mut down);
+        assert!(drawn.iter().all(|sels| !sels.is_empty()));
+        assert_eq!(drawn, selections(&mut up));
+
+        // within a single line as well
+        down.cursors[0] = Cursor { x: 4, y: 1, sel_x: -3, sel_y: 0, ..down.cursors[0] };
+        up.cursors[0] = Cursor { x: 1, y: 1, sel_x: 3, sel_y: 0, ..up.cursors[0] };
+
+        assert_eq!(selections(&mut down), selections(&mut up));
+    }
+
+    #[test]
+    fn short_lines_reset_horizontal_scroll() {
+        let text = format!("{}\nshort", "long ".repeat(20));
+        let mut tab = Tab::new(None, None, text);
+
+        tab.goto(100, 0);
+        tab.ensure_cursor_visible(20, 5);
+        assert!(tab.h_scroll > 0);
+
+        tab.vertical_jump(1, false);
+        tab.ensure_cursor_visible(20, 5);
+        assert_eq!(tab.h_scroll, 0);
+
+        tab.vertical_jump(-1, false);
+        tab.ensure_cursor_visible(20, 5);
+        assert!(tab.h_scroll > 0);
+    }
+
+    #[test]
+    fn visible_lines_share_horizontal_scroll() {
+        let long = "long ".repeat(20);
+        let text = format!("{long}\n{long}\nshort");
+        let mut tab = Tab::new(None, None, text);
+
+        // a cursor at the end of each long line
+        tab.goto(100, 0);
+        tab.add_cursor_vertical(false);
+        tab.ensure_cursor_visible(20, 5);
+        assert_eq!(tab.cursor_count(), 2);
+        assert!(tab.h_scroll > 0);
+
+        for y in 0..3 {
+            let (mut parts, mut sels, mut cursors) = (Vec::new(), Vec::new(), Vec::new());
+            let data = tab.line_data(y, &mut parts, &mut sels, &mut cursors);
+            assert_eq!(data.horizontal_scroll, tab.h_scroll);
+        }
+    }
 }
@@ -0,0 +1,99 @@
+use super::*;
+
+impl Tab {
+    // (x, y, length in chars) of each match
+    pub fn search_matches(&self, needle: &str) -> Vec<(usize, usize, usize)> {
+        let mut found = Vec::new();
+
+        if needle.is_empty() {
+            return found;
+        }
+
+        // invalid patterns are often incomplete ones
+        #[cfg(feature = "regex")]
+        match pattern::parse_regex(needle) {
+            Ok(Some(re)) => return self.regex_matches(&re),
+            Ok(None) => (),
+            Err(_) => return found,
+        }
+
+        let len = needle.chars().count();
+
+        // multi-line needles may overlap themselves
+        let step = match needle.contains('\n') {
+            true => 1,
+            false => needle.chars().count(),
+        };
+
+        let (mut x, mut y) = (0, 0);
+
+        while let Some((fx, fy)) = self.find(needle, x, y, false) {
+            found.push((fx, fy, len));
+            x = fx + step;
+            y = fy;
+        }
+
+        found
+    }
+
+    pub fn start_search(&mut self) {
+        self.search_backup = Some((self.cursors.clone(), self.v_scroll));
+    }
+
+    // highlights matches, returns their count
+    pub fn show_matches(&mut self, needle: &str) -> usize {
+        self.found = self.search_matches(needle);
+        self.set_lines_redraw();
+        self.found.len()
+    }
+
+    // index of the first match after the initial cursor
+    pub fn nearest_match(&self) -> Option<usize> {
+        let (cursors, _) = self.search_backup.as_ref()?;
+        let latest = cursors.iter().max_by_key(|c| c.id)?;
+        let origin = (latest.y, latest.x);
+
+        match self.found.iter().position(|(x, y, _)| (*y, *x) >= origin) {
+            Some(i) => Some(i),
+            None => (!self.found.is_empty()).then_some(0),
+        }
+    }
+
+    pub fn select_match(&mut self, index: usize) {
+        let Some(&(x, y, len)) = self.found.get(index) else {
+            return;
+        };
+
+        for cursor in &self.cursors {
+            self.lines[cursor.y].must_draw = true;
+        }
+
+        let mut cursor = Cursor::new(0);
+        cursor.x = x;
+        cursor.y = y;
+
+        self.cursors.clear();
+        self.cursors.push(cursor);
+        self.hor_jump_cursor(0, len as isize, true);
+    }
+
+    pub fn restore_cursors(&mut self) {
+        let Some((cursors, v_scroll)) = self.search_backup.as_ref() else {
+            return;
+        };
+
+        self.cursors.clone_from(cursors);
+        self.v_scroll = *v_scroll;
+        self.set_lines_redraw();
+    }
+
+    pub fn end_search(&mut self, restore: bool) {
+        if restore {
+            self.restore_cursors();
+        }
+
+        self.search_backup = None;
+        self.found.clear();
+        self.set_lines_redraw();
+    }
+}